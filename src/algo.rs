@@ -0,0 +1,12 @@
+//! Graph algorithms.
+//!
+//! Algorithms are generic over [`Adjacency`](crate::visit::Adjacency), so they
+//! work on both [`Graph`](crate::Graph) and [`ReadOnlyGraph`](crate::ReadOnlyGraph).
+//! Most of them are also available as methods on the graphs themselves.
+mod scc;
+mod shortest_path;
+mod toposort;
+
+pub use scc::{strongly_connected_components, Components};
+pub use shortest_path::{bellman_ford, dijkstra, ShortestPaths};
+pub use toposort::{toposort, toposort_ordered};
@@ -0,0 +1,227 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{visit::Adjacency, Direction, EdgeId, Graph, ReadOnlyGraph};
+
+/// The strongly connected components of a graph, found with
+/// [`strongly_connected_components`].
+#[derive(Debug)]
+pub struct Components<K> {
+    components: Vec<Vec<K>>,
+    membership: HashMap<K, usize>,
+    condensation: Graph<usize, Vec<K>, Vec<EdgeId>>,
+}
+
+impl<K: Eq + Hash> Components<K> {
+    /// Returns the number of components.
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Returns `true` if the graph had no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// The nodes in each component, indexed by component.
+    ///
+    /// Components are in topological order: edges between components always
+    /// point from a lower index to a higher one.
+    pub fn components(&self) -> &[Vec<K>] {
+        &self.components
+    }
+
+    /// Returns the index of the component containing `key`.
+    pub fn component_of(&self, key: &K) -> Option<usize> {
+        self.membership.get(key).copied()
+    }
+
+    /// The condensation of the graph: an acyclic graph with one node per
+    /// component, keyed by component index and storing the component's
+    /// members.
+    ///
+    /// There is at most one edge between each pair of components. It stores
+    /// the ids of all the original edges between them.
+    pub fn condensation(&self) -> &Graph<usize, Vec<K>, Vec<EdgeId>> {
+        &self.condensation
+    }
+
+    /// Consume the components, returning the [condensation](Components::condensation).
+    pub fn into_condensation(self) -> Graph<usize, Vec<K>, Vec<EdgeId>> {
+        self.condensation
+    }
+}
+
+/// Find the strongly connected components of a graph using Tarjan's algorithm.
+///
+/// Two nodes are in the same component if each can be reached from the other.
+pub fn strongly_connected_components<G: Adjacency>(graph: &G) -> Components<G::Key> {
+    let mut keys = Vec::with_capacity(graph.node_count());
+    graph.for_each_node(|key| keys.push(key.clone()));
+
+    let mut components = Tarjan::new(graph).run(&keys);
+    // Tarjan's algorithm finds components in reverse topological order
+    components.reverse();
+
+    let membership: HashMap<G::Key, usize> = components
+        .iter()
+        .enumerate()
+        .flat_map(|(i, component)| component.iter().map(move |key| (key.clone(), i)))
+        .collect();
+
+    let mut aggregated: BTreeMap<(usize, usize), Vec<EdgeId>> = BTreeMap::new();
+    for key in &keys {
+        let from = membership[key];
+        graph.for_each_neighbor(key, Direction::Outgoing, |edge_id, to| {
+            match membership.get(to) {
+                Some(&to) if to != from => aggregated.entry((from, to)).or_default().push(edge_id),
+                _ => {}
+            }
+        });
+    }
+
+    let condensation: Graph<usize, Vec<G::Key>, Vec<EdgeId>> =
+        components.iter().cloned().enumerate().collect();
+    for ((from, to), edge_ids) in aggregated {
+        condensation.add_edge(from, to, edge_ids);
+    }
+
+    Components {
+        components,
+        membership,
+        condensation,
+    }
+}
+
+struct NodeState {
+    index: usize,
+    lowlink: usize,
+    on_stack: bool,
+}
+
+/// Iterative Tarjan's algorithm, so deep graphs don't overflow the call stack.
+struct Tarjan<'g, G: Adjacency> {
+    graph: &'g G,
+    states: HashMap<G::Key, NodeState>,
+    stack: Vec<G::Key>,
+    /// Nodes being visited, their neighbors, and the next neighbor to visit.
+    call_stack: Vec<(G::Key, Vec<G::Key>, usize)>,
+    components: Vec<Vec<G::Key>>,
+}
+
+impl<'g, G: Adjacency> Tarjan<'g, G> {
+    fn new(graph: &'g G) -> Self {
+        Self {
+            graph,
+            states: HashMap::with_capacity(graph.node_count()),
+            stack: Vec::new(),
+            call_stack: Vec::new(),
+            components: Vec::new(),
+        }
+    }
+
+    fn run(mut self, keys: &[G::Key]) -> Vec<Vec<G::Key>> {
+        for root in keys {
+            if self.states.contains_key(root) {
+                continue;
+            }
+            self.visit(root.clone());
+            self.resume();
+        }
+        self.components
+    }
+
+    fn visit(&mut self, key: G::Key) {
+        let index = self.states.len();
+        self.states.insert(
+            key.clone(),
+            NodeState {
+                index,
+                lowlink: index,
+                on_stack: true,
+            },
+        );
+        self.stack.push(key.clone());
+
+        let mut neighbors = Vec::new();
+        self.graph
+            .for_each_neighbor(&key, Direction::Outgoing, |_, neighbor| {
+                neighbors.push(neighbor.clone());
+            });
+        self.call_stack.push((key, neighbors, 0));
+    }
+
+    fn resume(&mut self) {
+        while let Some((key, neighbors, next)) = self.call_stack.last_mut() {
+            if let Some(neighbor) = neighbors.get(*next).cloned() {
+                *next += 1;
+                match self.states.get(&neighbor) {
+                    None if self.graph.contains_node(&neighbor) => self.visit(neighbor),
+                    Some(state) if state.on_stack => {
+                        let index = state.index;
+                        let state = self.states.get_mut(key).unwrap();
+                        state.lowlink = state.lowlink.min(index);
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            let (key, _, _) = self.call_stack.pop().unwrap();
+            let state = &self.states[&key];
+            let (index, lowlink) = (state.index, state.lowlink);
+            if let Some((parent, _, _)) = self.call_stack.last() {
+                let parent = self.states.get_mut(parent).unwrap();
+                parent.lowlink = parent.lowlink.min(lowlink);
+            }
+
+            if index == lowlink {
+                let mut component = Vec::new();
+                loop {
+                    let member = self.stack.pop().unwrap();
+                    self.states.get_mut(&member).unwrap().on_stack = false;
+                    let is_root = member == key;
+                    component.push(member);
+                    if is_root {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+}
+
+impl<K, V, E, S> ReadOnlyGraph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    /// Find the graph's strongly connected components. See
+    /// [`algo::strongly_connected_components`](crate::algo::strongly_connected_components).
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, ()> = ["a", "b", "c"].into_iter().map(|k| (k, ())).collect();
+    /// graph.add_edge("a", "b", ());
+    /// graph.add_edge("b", "a", ());
+    /// graph.add_edge("b", "c", ());
+    ///
+    /// let sccs = graph.into_read_only().strongly_connected_components();
+    /// assert_eq!(sccs.len(), 2);
+    /// assert_eq!(sccs.component_of(&"a"), sccs.component_of(&"b"));
+    /// assert_ne!(sccs.component_of(&"a"), sccs.component_of(&"c"));
+    ///
+    /// // Components are collapsed into a single node
+    /// let condensation = sccs.condensation();
+    /// assert_eq!(condensation.len(), 2);
+    /// assert_eq!(condensation.edges_from(&0).unwrap().len(), 1);
+    /// ```
+    pub fn strongly_connected_components(&self) -> Components<K> {
+        strongly_connected_components(self)
+    }
+}
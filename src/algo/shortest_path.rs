@@ -0,0 +1,296 @@
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::Entry, BinaryHeap, HashMap, HashSet},
+    hash::{BuildHasher, Hash},
+    ops::Add,
+};
+
+use crate::{visit::EdgeData, Cycle, Direction, EdgeId, Graph, ReadOnlyGraph};
+
+/// Shortest paths from a single start node, found with [`dijkstra`] or
+/// [`bellman_ford`].
+///
+/// Only nodes reachable from the start node have a distance.
+#[derive(Debug, Clone)]
+pub struct ShortestPaths<K, W> {
+    distances: HashMap<K, W>,
+    predecessors: HashMap<K, (K, EdgeId)>,
+}
+
+impl<K: Eq + Hash + Clone, W: Copy> ShortestPaths<K, W> {
+    /// Returns the length of the shortest path to `key`, or `None` if it is
+    /// unreachable.
+    pub fn distance(&self, key: &K) -> Option<W> {
+        self.distances.get(key).copied()
+    }
+
+    /// Returns the node before `key` on the shortest path to it, along with
+    /// the edge between them. Returns `None` for the start node and
+    /// unreachable nodes.
+    pub fn predecessor(&self, key: &K) -> Option<(&K, EdgeId)> {
+        self.predecessors
+            .get(key)
+            .map(|(predecessor, edge_id)| (predecessor, *edge_id))
+    }
+
+    /// Returns the nodes on the shortest path to `key`, starting with the
+    /// start node and ending with `key`.
+    pub fn path_to(&self, key: &K) -> Option<Vec<K>> {
+        if !self.distances.contains_key(key) {
+            return None;
+        }
+        let mut path = vec![key.clone()];
+        let mut current = key;
+        while let Some((predecessor, _)) = self.predecessors.get(current) {
+            path.push(predecessor.clone());
+            current = predecessor;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// The length of the shortest path to every reachable node.
+    pub fn distances(&self) -> &HashMap<K, W> {
+        &self.distances
+    }
+
+    /// The predecessor of every reachable node except the start node, along
+    /// with the edge between them.
+    pub fn predecessors(&self) -> &HashMap<K, (K, EdgeId)> {
+        &self.predecessors
+    }
+}
+
+/// Find the shortest paths from `start` to every reachable node using
+/// Dijkstra's algorithm.
+///
+/// Edge weights are computed from edge data by `weight`. [`W::default()`](Default::default)
+/// is used as the length of an empty path. Weights must not be negative; use
+/// [`bellman_ford`] for graphs with negative weights.
+pub fn dijkstra<G, W, F>(graph: &G, start: G::Key, weight: F) -> ShortestPaths<G::Key, W>
+where
+    G: EdgeData,
+    W: Ord + Add<Output = W> + Default + Copy,
+    F: Fn(&G::Edge) -> W,
+{
+    let mut distances = HashMap::new();
+    let mut predecessors = HashMap::new();
+    if !graph.contains_node(&start) {
+        return ShortestPaths {
+            distances,
+            predecessors,
+        };
+    }
+
+    let mut visited = HashSet::new();
+    let mut queue = BinaryHeap::new();
+    distances.insert(start.clone(), W::default());
+    queue.push(MinScored(W::default(), start));
+
+    while let Some(MinScored(distance, key)) = queue.pop() {
+        if !visited.insert(key.clone()) {
+            continue;
+        }
+        graph.for_each_neighbor(&key, Direction::Outgoing, |edge_id, to| {
+            if visited.contains(to) {
+                return;
+            }
+            let Some(next) = graph.with_edge(edge_id, |edge| distance + weight(edge)) else {
+                return;
+            };
+            match distances.entry(to.clone()) {
+                Entry::Occupied(mut entry) if next < *entry.get() => {
+                    entry.insert(next);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(next);
+                }
+                Entry::Occupied(_) => return,
+            }
+            predecessors.insert(to.clone(), (key.clone(), edge_id));
+            queue.push(MinScored(next, to.clone()));
+        });
+    }
+
+    ShortestPaths {
+        distances,
+        predecessors,
+    }
+}
+
+/// Find the shortest paths from `start` to every reachable node using the
+/// Bellman-Ford algorithm.
+///
+/// Unlike [`dijkstra`], edge weights may be negative. This is slower, taking
+/// `O(nodes * edges)` time.
+///
+/// # Errors
+/// Returns a [`Cycle`] with negative total weight if one can be reached from
+/// `start`, since shortest paths are undefined in that case.
+pub fn bellman_ford<G, W, F>(
+    graph: &G,
+    start: G::Key,
+    weight: F,
+) -> Result<ShortestPaths<G::Key, W>, Cycle<G::Key>>
+where
+    G: EdgeData,
+    W: Ord + Add<Output = W> + Default + Copy,
+    F: Fn(&G::Edge) -> W,
+{
+    let mut distances = HashMap::new();
+    let mut predecessors = HashMap::new();
+    if !graph.contains_node(&start) {
+        return Ok(ShortestPaths {
+            distances,
+            predecessors,
+        });
+    }
+
+    let mut edges = Vec::new();
+    graph.for_each_node(|from| {
+        graph.for_each_neighbor(from, Direction::Outgoing, |edge_id, to| {
+            if let Some(weight) = graph.with_edge(edge_id, &weight) {
+                edges.push((from.clone(), edge_id, weight, to.clone()));
+            }
+        });
+    });
+
+    // Returns the node whose distance was lowered, if any
+    let relax = |distances: &mut HashMap<G::Key, W>,
+                 predecessors: &mut HashMap<G::Key, (G::Key, EdgeId)>| {
+        let mut relaxed = None;
+        for (from, edge_id, weight, to) in &edges {
+            let Some(&distance) = distances.get(from) else {
+                continue;
+            };
+            let next = distance + *weight;
+            if distances.get(to).map_or(true, |current| next < *current) {
+                distances.insert(to.clone(), next);
+                predecessors.insert(to.clone(), (from.clone(), *edge_id));
+                relaxed = Some(to.clone());
+            }
+        }
+        relaxed
+    };
+
+    distances.insert(start, W::default());
+    for _ in 1..graph.node_count() {
+        if relax(&mut distances, &mut predecessors).is_none() {
+            break;
+        }
+    }
+
+    let Some(relaxed) = relax(&mut distances, &mut predecessors) else {
+        return Ok(ShortestPaths {
+            distances,
+            predecessors,
+        });
+    };
+
+    // A node relaxed after `n - 1` rounds is reachable from a negative cycle.
+    // Walking `n` predecessors back from it is guaranteed to land on the cycle.
+    let mut on_cycle = relaxed;
+    for _ in 0..graph.node_count() {
+        on_cycle = predecessors[&on_cycle].0.clone();
+    }
+    let mut path = vec![on_cycle.clone()];
+    let mut current = predecessors[&on_cycle].0.clone();
+    while current != on_cycle {
+        let predecessor = predecessors[&current].0.clone();
+        path.push(current);
+        current = predecessor;
+    }
+    path.reverse();
+    path.rotate_right(1);
+    Err(Cycle { path })
+}
+
+/// A key in a priority queue, ordered so that the smallest score is popped
+/// from a [`BinaryHeap`] first.
+struct MinScored<W, K>(W, K);
+
+impl<W: Ord, K> PartialEq for MinScored<W, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<W: Ord, K> Eq for MinScored<W, K> {}
+
+impl<W: Ord, K> PartialOrd for MinScored<W, K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<W: Ord, K> Ord for MinScored<W, K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+impl<K, V, E, S> Graph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    /// Find the shortest paths from `start` using edge weights computed by
+    /// `weight`. See [`algo::dijkstra`](crate::algo::dijkstra).
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, (), u32> = ["a", "b", "c"].into_iter().map(|k| (k, ())).collect();
+    /// graph.add_edge("a", "b", 5);
+    /// graph.add_edge("a", "c", 1);
+    /// graph.add_edge("c", "b", 2);
+    ///
+    /// let paths = graph.dijkstra("a", |weight| *weight);
+    /// assert_eq!(paths.distance(&"b"), Some(3));
+    /// assert_eq!(paths.path_to(&"b"), Some(vec!["a", "c", "b"]));
+    /// ```
+    pub fn dijkstra<W, F>(&self, start: K, weight: F) -> ShortestPaths<K, W>
+    where
+        W: Ord + Add<Output = W> + Default + Copy,
+        F: Fn(&E) -> W,
+    {
+        dijkstra(self, start, weight)
+    }
+
+    /// Find the shortest paths from `start` using edge weights computed by
+    /// `weight`, which may be negative. See [`algo::bellman_ford`](crate::algo::bellman_ford).
+    pub fn bellman_ford<W, F>(&self, start: K, weight: F) -> Result<ShortestPaths<K, W>, Cycle<K>>
+    where
+        W: Ord + Add<Output = W> + Default + Copy,
+        F: Fn(&E) -> W,
+    {
+        bellman_ford(self, start, weight)
+    }
+}
+
+impl<K, V, E, S> ReadOnlyGraph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    /// Find the shortest paths from `start` using edge weights computed by
+    /// `weight`. See [`algo::dijkstra`](crate::algo::dijkstra).
+    pub fn dijkstra<W, F>(&self, start: K, weight: F) -> ShortestPaths<K, W>
+    where
+        W: Ord + Add<Output = W> + Default + Copy,
+        F: Fn(&E) -> W,
+    {
+        dijkstra(self, start, weight)
+    }
+
+    /// Find the shortest paths from `start` using edge weights computed by
+    /// `weight`, which may be negative. See [`algo::bellman_ford`](crate::algo::bellman_ford).
+    pub fn bellman_ford<W, F>(&self, start: K, weight: F) -> Result<ShortestPaths<K, W>, Cycle<K>>
+    where
+        W: Ord + Add<Output = W> + Default + Copy,
+        F: Fn(&E) -> W,
+    {
+        bellman_ford(self, start, weight)
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, VecDeque},
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{visit::Adjacency, Cycle, Direction, Graph, ReadOnlyGraph};
+
+/// Sort a graph's nodes so that every node comes before the nodes its edges
+/// point to.
+///
+/// Nodes with no ordering constraint between them come out in an unspecified
+/// order. Use [`toposort_ordered`] for a deterministic ordering.
+///
+/// # Errors
+/// Returns a [`Cycle`] if the graph is not acyclic.
+pub fn toposort<G: Adjacency>(graph: &G) -> Result<Vec<G::Key>, Cycle<G::Key>> {
+    kahn(graph, VecDeque::new(), |_| {})
+}
+
+/// Like [`toposort`], but breaks ties between nodes by their keys, smallest
+/// first. The resulting order only depends on the graph's contents.
+///
+/// # Errors
+/// Returns a [`Cycle`] if the graph is not acyclic.
+pub fn toposort_ordered<G>(graph: &G) -> Result<Vec<G::Key>, Cycle<G::Key>>
+where
+    G: Adjacency,
+    G::Key: Ord,
+{
+    kahn(graph, BinaryHeap::new(), |keys| keys.sort())
+}
+
+/// Nodes with no remaining incoming edges, waiting to be emitted.
+trait Ready<K> {
+    fn push(&mut self, key: K);
+    fn pop(&mut self) -> Option<K>;
+}
+
+impl<K> Ready<K> for VecDeque<K> {
+    fn push(&mut self, key: K) {
+        self.push_back(key);
+    }
+    fn pop(&mut self) -> Option<K> {
+        self.pop_front()
+    }
+}
+
+impl<K: Ord> Ready<K> for BinaryHeap<Reverse<K>> {
+    fn push(&mut self, key: K) {
+        BinaryHeap::push(self, Reverse(key));
+    }
+    fn pop(&mut self) -> Option<K> {
+        BinaryHeap::pop(self).map(|Reverse(key)| key)
+    }
+}
+
+/// Kahn's algorithm. Nodes are numbered in the order `order` leaves them in,
+/// and `ready` decides which node with no remaining incoming edges is emitted
+/// next.
+fn kahn<G, R>(
+    graph: &G,
+    mut ready: R,
+    order: impl FnOnce(&mut [G::Key]),
+) -> Result<Vec<G::Key>, Cycle<G::Key>>
+where
+    G: Adjacency,
+    R: Ready<usize>,
+{
+    // Read every adjacency list exactly once. Graphs may be written to while
+    // they are sorted, so later passes must not go back to the graph and see
+    // different edges.
+    let mut keys = Vec::with_capacity(graph.node_count());
+    graph.for_each_node(|key| keys.push(key.clone()));
+    order(&mut keys);
+    let index: HashMap<G::Key, usize> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key.clone(), i))
+        .collect();
+    let successors: Vec<Vec<usize>> = keys
+        .iter()
+        .map(|key| {
+            let mut successors = Vec::new();
+            graph.for_each_neighbor(key, Direction::Outgoing, |_, to| {
+                successors.extend(index.get(to));
+            });
+            successors
+        })
+        .collect();
+
+    let mut in_degree = vec![0; keys.len()];
+    for to in successors.iter().flatten() {
+        in_degree[*to] += 1;
+    }
+
+    for (i, degree) in in_degree.iter().enumerate() {
+        if *degree == 0 {
+            ready.push(i);
+        }
+    }
+
+    let mut sorted = Vec::with_capacity(keys.len());
+    while let Some(i) = ready.pop() {
+        for to in &successors[i] {
+            in_degree[*to] -= 1;
+            if in_degree[*to] == 0 {
+                ready.push(*to);
+            }
+        }
+        sorted.push(i);
+    }
+
+    if sorted.len() == keys.len() {
+        Ok(sorted.into_iter().map(|i| keys[i].clone()).collect())
+    } else {
+        let path = find_cycle(&successors, &in_degree);
+        Err(Cycle {
+            path: path.into_iter().map(|i| keys[i].clone()).collect(),
+        })
+    }
+}
+
+/// Find a cycle among the nodes Kahn's algorithm could not emit, given the
+/// adjacency lists it sorted and the in-degrees it left behind.
+///
+/// Every such node has an incoming edge from another such node, so walking
+/// those edges backwards must eventually revisit a node.
+fn find_cycle(successors: &[Vec<usize>], in_degree: &[usize]) -> Vec<usize> {
+    let is_blocked = |i: usize| in_degree[i] > 0;
+    let mut predecessors = vec![Vec::new(); successors.len()];
+    for (from, successors) in successors.iter().enumerate() {
+        if is_blocked(from) {
+            for to in successors {
+                predecessors[*to].push(from);
+            }
+        }
+    }
+
+    let mut path = Vec::new();
+    let mut positions: Vec<Option<usize>> = vec![None; successors.len()];
+    let mut current = (0..successors.len()).find(|i| is_blocked(*i));
+    while let Some(i) = current {
+        if let Some(start) = positions[i] {
+            let mut path = path.split_off(start);
+            // The path was built by walking edges backwards
+            path.reverse();
+            path.rotate_right(1);
+            return path;
+        }
+        positions[i] = Some(path.len());
+        path.push(i);
+        current = predecessors[i].first().copied();
+    }
+    // Unreachable, since the adjacency lists cannot change while they are
+    // walked. Report the blocked nodes rather than panicking.
+    path
+}
+
+impl<K, V, E, S> Graph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    /// Sort the graph's nodes so that every node comes before the nodes its
+    /// edges point to. See [`algo::toposort`](fn@crate::algo::toposort).
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, ()> = ["std", "core", "alloc"].into_iter().map(|k| (k, ())).collect();
+    /// graph.add_edge("core", "alloc", ());
+    /// graph.add_edge("alloc", "std", ());
+    /// assert_eq!(graph.toposort().unwrap(), vec!["core", "alloc", "std"]);
+    ///
+    /// graph.add_edge("std", "core", ());
+    /// let cycle = graph.toposort().unwrap_err();
+    /// assert_eq!(cycle.path().len(), 3);
+    /// ```
+    pub fn toposort(&self) -> Result<Vec<K>, Cycle<K>> {
+        toposort(self)
+    }
+
+    /// Like [`Graph::toposort`], but breaks ties between nodes by their keys.
+    /// See [`algo::toposort_ordered`](crate::algo::toposort_ordered).
+    pub fn toposort_ordered(&self) -> Result<Vec<K>, Cycle<K>>
+    where
+        K: Ord,
+    {
+        toposort_ordered(self)
+    }
+}
+
+impl<K, V, E, S> ReadOnlyGraph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    /// Sort the graph's nodes so that every node comes before the nodes its
+    /// edges point to. See [`algo::toposort`](fn@crate::algo::toposort).
+    pub fn toposort(&self) -> Result<Vec<K>, Cycle<K>> {
+        toposort(self)
+    }
+
+    /// Like [`ReadOnlyGraph::toposort`], but breaks ties between nodes by
+    /// their keys. See [`algo::toposort_ordered`](crate::algo::toposort_ordered).
+    pub fn toposort_ordered(&self) -> Result<Vec<K>, Cycle<K>>
+    where
+        K: Ord,
+    {
+        toposort_ordered(self)
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    ops::Range,
+};
+
+use crate::{DefaultHasher, EdgeHasher, EdgeId, Graph};
+
+/// A frozen graph stored in compressed sparse row form, created with
+/// [`Graph::freeze_csr`].
+///
+/// Nodes are numbered with dense `u32` indices. Each node's outgoing and
+/// incoming edges are stored contiguously, so walking adjacency lists never
+/// chases pointers or takes locks. Prefer it over a
+/// [`ReadOnlyGraph`](crate::ReadOnlyGraph) for repeated passes over an entire
+/// graph.
+///
+/// Outgoing edges keep the order they had in the [`Graph`]. Incoming edges
+/// are ordered by the index of their source node.
+#[derive(Clone)]
+pub struct CsrGraph<K, V, E = (), S = DefaultHasher> {
+    keys: Vec<K>,
+    values: Vec<V>,
+    index: HashMap<K, u32, S>,
+    /// Outgoing edges of node `i` are at `out_offsets[i]..out_offsets[i + 1]`.
+    out_offsets: Vec<usize>,
+    out_targets: Vec<u32>,
+    out_edge_ids: Vec<EdgeId>,
+    /// Edge data, in the same order as the outgoing edges.
+    edge_data: Vec<E>,
+    /// Incoming edges of node `i` are at `in_offsets[i]..in_offsets[i + 1]`.
+    in_offsets: Vec<usize>,
+    in_sources: Vec<u32>,
+    /// Position of each incoming edge in the outgoing edge arrays.
+    in_positions: Vec<usize>,
+    /// Position of each edge in the outgoing edge arrays.
+    edge_positions: HashMap<EdgeId, usize, EdgeHasher>,
+}
+
+impl<K, V, E, S> CsrGraph<K, V, E, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the number of nodes in the graph.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns the number of edges in the graph.
+    pub fn edge_count(&self) -> usize {
+        self.edge_data.len()
+    }
+
+    /// Returns the index of the node with the given `key`.
+    pub fn index_of<Q>(&self, key: &Q) -> Option<u32>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.get(key).copied()
+    }
+
+    pub fn has_node<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index.contains_key(key)
+    }
+
+    pub fn get_node<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index_of(key).map(|index| &self.values[index as usize])
+    }
+
+    pub fn get_edge(&self, edge_id: EdgeId) -> Option<&E> {
+        self.edge_positions
+            .get(&edge_id)
+            .map(|position| &self.edge_data[*position])
+    }
+
+    /// Returns the key of the node at `index`.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn key(&self, index: u32) -> &K {
+        &self.keys[index as usize]
+    }
+
+    /// Returns the value of the node at `index`.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn value(&self, index: u32) -> &V {
+        &self.values[index as usize]
+    }
+
+    /// Every node's key, indexed by node index.
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
+    /// Every node's value, indexed by node index.
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+
+    /// Returns the indices of the targets of the edges originating from the
+    /// node at `index`.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn out_neighbors(&self, index: u32) -> &[u32] {
+        &self.out_targets[self.out_range(index)]
+    }
+
+    /// Returns the indices of the sources of the edges terminating at the
+    /// node at `index`.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn in_neighbors(&self, index: u32) -> &[u32] {
+        &self.in_sources[self.in_range(index)]
+    }
+
+    /// Returns the id, data, and target index of every edge originating from
+    /// the node at `index`.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn out_edges(&self, index: u32) -> impl Iterator<Item = (EdgeId, &E, u32)> {
+        self.out_range(index).map(|position| {
+            (
+                self.out_edge_ids[position],
+                &self.edge_data[position],
+                self.out_targets[position],
+            )
+        })
+    }
+
+    /// Returns the id, data, and source index of every edge terminating at
+    /// the node at `index`.
+    ///
+    /// # Panics
+    /// If `index` is out of bounds.
+    pub fn in_edges(&self, index: u32) -> impl Iterator<Item = (EdgeId, &E, u32)> {
+        let range = self.in_range(index);
+        self.in_positions[range.clone()]
+            .iter()
+            .zip(&self.in_sources[range])
+            .map(|(position, source)| {
+                (
+                    self.out_edge_ids[*position],
+                    &self.edge_data[*position],
+                    *source,
+                )
+            })
+    }
+
+    /// Iterate over all nodes in the graph, in index order.
+    pub fn iter_nodes(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.keys.iter().zip(&self.values)
+    }
+
+    fn out_range(&self, index: u32) -> Range<usize> {
+        let index = index as usize;
+        self.out_offsets[index]..self.out_offsets[index + 1]
+    }
+
+    fn in_range(&self, index: u32) -> Range<usize> {
+        let index = index as usize;
+        self.in_offsets[index]..self.in_offsets[index + 1]
+    }
+}
+
+impl<K, V, E, S> Debug for CsrGraph<K, V, E, S>
+where
+    K: Debug,
+    V: Debug,
+    E: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CsrGraph")
+            .field("keys", &self.keys)
+            .field("values", &self.values)
+            .field("out_offsets", &self.out_offsets)
+            .field("out_targets", &self.out_targets)
+            .field("out_edge_ids", &self.out_edge_ids)
+            .field("edge_data", &self.edge_data)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, V, E, S> Graph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    /// Freeze the graph into a [`CsrGraph`], which is faster to read than a
+    /// [`ReadOnlyGraph`](crate::ReadOnlyGraph).
+    ///
+    /// Edges that refer to missing nodes or have no edge data are dropped.
+    ///
+    /// # Panics
+    /// If the graph has more than `u32::MAX` nodes.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, u32, f64> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+    /// graph.add_edge("a", "b", 0.5);
+    /// graph.add_edge("a", "c", 1.5);
+    /// graph.add_edge("b", "c", 2.5);
+    ///
+    /// let csr = graph.freeze_csr();
+    /// let a = csr.index_of("a").unwrap();
+    /// let c = csr.index_of("c").unwrap();
+    /// assert_eq!(csr.out_neighbors(a).len(), 2);
+    /// assert_eq!(csr.in_neighbors(c).len(), 2);
+    /// assert_eq!(*csr.value(c), 3);
+    /// ```
+    pub fn freeze_csr(self) -> CsrGraph<K, V, E, S> {
+        let Graph {
+            nodes, edges, from, ..
+        } = self;
+        let node_count = nodes.len();
+        assert!(
+            u32::try_from(node_count).is_ok(),
+            "graph has too many nodes to freeze"
+        );
+
+        let mut index = HashMap::with_capacity_and_hasher(node_count, nodes.hasher().clone());
+        let mut keys = Vec::with_capacity(node_count);
+        let mut values = Vec::with_capacity(node_count);
+        for (i, (key, value)) in nodes.into_iter().enumerate() {
+            index.insert(key.clone(), i as u32);
+            keys.push(key);
+            values.push(value);
+        }
+
+        let edge_count = edges.len();
+        let mut out_offsets = Vec::with_capacity(node_count + 1);
+        let mut out_targets = Vec::with_capacity(edge_count);
+        let mut out_edge_ids = Vec::with_capacity(edge_count);
+        let mut edge_data = Vec::with_capacity(edge_count);
+        out_offsets.push(0);
+        for key in &keys {
+            for (edge_id, to) in from.remove(key).map(|(_, edges)| edges).unwrap_or_default() {
+                let Some(&target) = index.get(&to) else {
+                    continue;
+                };
+                let Some((_, (_, edge, _))) = edges.remove(&edge_id) else {
+                    continue;
+                };
+                out_targets.push(target);
+                out_edge_ids.push(edge_id);
+                edge_data.push(edge);
+            }
+            out_offsets.push(out_targets.len());
+        }
+
+        // Bucket the outgoing edges by target to find the incoming edges
+        let mut in_offsets = vec![0; node_count + 1];
+        for target in &out_targets {
+            in_offsets[*target as usize + 1] += 1;
+        }
+        for i in 0..node_count {
+            in_offsets[i + 1] += in_offsets[i];
+        }
+        let mut next = in_offsets.clone();
+        let mut in_sources = vec![0; out_targets.len()];
+        let mut in_positions = vec![0; out_targets.len()];
+        for source in 0..node_count {
+            for position in out_offsets[source]..out_offsets[source + 1] {
+                let slot = &mut next[out_targets[position] as usize];
+                in_sources[*slot] = source as u32;
+                in_positions[*slot] = position;
+                *slot += 1;
+            }
+        }
+
+        let edge_positions = out_edge_ids
+            .iter()
+            .enumerate()
+            .map(|(position, edge_id)| (*edge_id, position))
+            .collect();
+
+        CsrGraph {
+            keys,
+            values,
+            index,
+            out_offsets,
+            out_targets,
+            out_edge_ids,
+            edge_data,
+            in_offsets,
+            in_sources,
+            in_positions,
+            edge_positions,
+        }
+    }
+}
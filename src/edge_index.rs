@@ -0,0 +1,295 @@
+//! Looking up the edges between two nodes, with an optional index.
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+};
+
+use dashmap::DashMap;
+
+use crate::{Direction, EdgeId, Graph, ReadOnlyGraph};
+
+/// Edges grouped by source, then by target.
+pub(crate) type EdgeIndex<K, S> = DashMap<K, HashMap<K, Vec<EdgeId>, S>, S>;
+
+impl<'a, K, V, E, S> Graph<K, V, E, S>
+where
+    K: 'a + Eq + Hash + Clone,
+    V: 'a,
+    E: 'a,
+    S: BuildHasher + Clone,
+{
+    /// Index edges by their source and target, making [`Graph::find_edge`]
+    /// and friends O(1) instead of linear in the degree of the nodes.
+    ///
+    /// The index is kept up to date as edges are added and removed, which
+    /// costs extra memory and makes writes slightly slower. It is worth
+    /// enabling for graphs with high-degree nodes. Does nothing if the index
+    /// is already enabled.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let mut graph: Graph<u32, ()> = (0..1000).map(|i| (i, ())).collect();
+    /// for i in 1..1000 {
+    ///     graph.add_edge(0, i, ());
+    /// }
+    /// graph.enable_edge_index();
+    ///
+    /// let edge_id = graph.add_edge(0, 500, ());
+    /// assert_eq!(graph.edges_connecting(&0, &500).len(), 2);
+    /// assert_eq!(graph.edges_connecting(&0, &500)[1], edge_id);
+    /// ```
+    pub fn enable_edge_index(&mut self) {
+        if self.edge_index.is_some() {
+            return;
+        }
+        let hasher = self.nodes.hasher().clone();
+        let index = DashMap::with_capacity_and_hasher(self.from.len(), hasher.clone());
+        for outgoing in self.from.iter() {
+            let mut targets: HashMap<K, Vec<EdgeId>, S> = HashMap::with_hasher(hasher.clone());
+            for (edge_id, to) in outgoing.iter() {
+                targets.entry(to.clone()).or_default().push(*edge_id);
+            }
+            index.insert(outgoing.key().clone(), targets);
+        }
+        self.edge_index = Some(index);
+    }
+
+    /// Drop the index created by [`Graph::enable_edge_index`], freeing its
+    /// memory.
+    pub fn disable_edge_index(&mut self) {
+        self.edge_index = None;
+    }
+
+    /// Returns `true` if edges are indexed by their source and target. See
+    /// [`Graph::enable_edge_index`].
+    pub fn has_edge_index(&self) -> bool {
+        self.edge_index.is_some()
+    }
+
+    /// Returns the id of an edge originating at `from` and terminating at
+    /// `to`. If there are several, one of them is returned.
+    ///
+    /// Without an [edge index](Graph::enable_edge_index), this scans the
+    /// shorter of `from`'s outgoing and `to`'s incoming adjacency lists.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, ()> = [("a", ()), ("b", ())].into_iter().collect();
+    /// let edge_id = graph.add_edge("a", "b", ());
+    ///
+    /// assert_eq!(graph.find_edge("a", "b"), Some(edge_id));
+    /// assert_eq!(graph.find_edge("b", "a"), None);
+    /// assert!(graph.contains_edge("a", "b"));
+    /// ```
+    pub fn find_edge<Q>(&self, from: &Q, to: &Q) -> Option<EdgeId>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match &self.edge_index {
+            Some(index) => index.get(from)?.get(to)?.first().copied(),
+            None => self.scan_connecting(from, to, |edges| edges.next()),
+        }
+    }
+
+    /// Returns the ids of every edge originating at `from` and terminating at
+    /// `to`, in the order they were added.
+    pub fn edges_connecting<Q>(&self, from: &Q, to: &Q) -> Vec<EdgeId>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match &self.edge_index {
+            Some(index) => index
+                .get(from)
+                .and_then(|targets| targets.get(to).cloned())
+                .unwrap_or_default(),
+            None => self
+                .scan_connecting(from, to, |edges| Some(edges.collect()))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns `true` if there is an edge originating at `from` and
+    /// terminating at `to`.
+    pub fn contains_edge<Q>(&self, from: &Q, to: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find_edge(from, to).is_some()
+    }
+
+    /// Call `f` with the ids of the edges between `from` and `to`, found by
+    /// scanning the shorter adjacency list.
+    fn scan_connecting<Q, R>(
+        &self,
+        from: &Q,
+        to: &Q,
+        f: impl FnOnce(&mut dyn Iterator<Item = EdgeId>) -> Option<R>,
+    ) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let outgoing = self.edges_directed(from, Direction::Outgoing)?;
+        let incoming = self.edges_directed(to, Direction::Incoming)?;
+        let (edges, other) = if outgoing.len() <= incoming.len() {
+            (&*outgoing, to)
+        } else {
+            (&*incoming, from)
+        };
+        f(&mut edges
+            .iter()
+            .filter(|(_, neighbor)| neighbor.borrow() == other)
+            .map(|(edge_id, _)| *edge_id))
+    }
+}
+
+impl<K, V, E, S> Graph<K, V, E, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    /// Record a new edge in the edge index, if there is one.
+    pub(crate) fn index_edge(&self, edge_id: EdgeId, from: &K, to: &K)
+    where
+        K: Clone,
+    {
+        if let Some(index) = &self.edge_index {
+            index
+                .entry(from.clone())
+                .or_insert_with(|| HashMap::with_hasher(self.nodes.hasher().clone()))
+                .entry(to.clone())
+                .or_default()
+                .push(edge_id);
+        }
+    }
+
+    /// Remove an edge from the edge index, if there is one.
+    pub(crate) fn unindex_edge(&self, edge_id: EdgeId, from: &K, to: &K) {
+        let Some(index) = &self.edge_index else {
+            return;
+        };
+        if let Some(mut targets) = index.get_mut(from) {
+            if let Some(edges) = targets.get_mut(to) {
+                edges.retain(|id| *id != edge_id);
+                if edges.is_empty() {
+                    targets.remove(to);
+                }
+            }
+        }
+        index.remove_if(from, |_, targets| targets.is_empty());
+    }
+
+    /// Free excess memory in the edge index, if there is one. With `all`, the
+    /// index's per-node maps and edge lists are shrunk too.
+    pub(crate) fn shrink_edge_index(&mut self, all: bool) {
+        let Some(index) = &mut self.edge_index else {
+            return;
+        };
+        if all {
+            for mut targets in index.iter_mut() {
+                for edges in targets.values_mut() {
+                    edges.shrink_to_fit();
+                }
+                targets.shrink_to_fit();
+            }
+        }
+        index.shrink_to_fit();
+    }
+
+    /// Remove every edge incident to `key` from the edge index, if there is
+    /// one. `sources` are the nodes with edges terminating at `key`.
+    pub(crate) fn unindex_node<'k, Q>(&self, key: &Q, sources: impl Iterator<Item = &'k K>)
+    where
+        K: Borrow<Q> + 'k,
+        Q: Hash + Eq + ?Sized,
+    {
+        let Some(index) = &self.edge_index else {
+            return;
+        };
+        index.remove(key);
+        for from in sources {
+            if let Some(mut targets) = index.get_mut::<K>(from) {
+                targets.remove(key);
+            }
+            index.remove_if::<K>(from, |_, targets| targets.is_empty());
+        }
+    }
+}
+
+impl<'a, K, V, E, S> ReadOnlyGraph<K, V, E, S>
+where
+    K: 'a + Eq + Hash,
+    V: 'a,
+    E: 'a,
+    S: 'a + BuildHasher + Clone,
+{
+    /// Returns the id of an edge originating at `from` and terminating at
+    /// `to`.
+    ///
+    /// This scans the shorter of `from`'s outgoing and `to`'s incoming
+    /// adjacency lists.
+    pub fn find_edge<Q>(&self, from: &Q, to: &Q) -> Option<EdgeId>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.edges_connecting(from, to).next()
+    }
+
+    /// Returns the ids of every edge originating at `from` and terminating at
+    /// `to`.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, ()> = [("a", ()), ("b", ())].into_iter().collect();
+    /// graph.add_edge("a", "b", ());
+    /// graph.add_edge("a", "b", ());
+    /// let graph = graph.into_read_only();
+    ///
+    /// assert_eq!(graph.edges_connecting("a", "b").count(), 2);
+    /// assert!(!graph.contains_edge("b", "a"));
+    /// ```
+    pub fn edges_connecting<Q>(
+        &'a self,
+        from: &'a Q,
+        to: &'a Q,
+    ) -> impl Iterator<Item = EdgeId> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let outgoing = self.edges_directed(from, Direction::Outgoing);
+        let incoming = self.edges_directed(to, Direction::Incoming);
+        let (edges, other) = match (outgoing, incoming) {
+            (Some(outgoing), Some(incoming)) if outgoing.len() <= incoming.len() => {
+                (&outgoing[..], to)
+            }
+            (Some(_), Some(incoming)) => (&incoming[..], from),
+            _ => (&[][..], to),
+        };
+        edges
+            .iter()
+            .filter(move |(_, neighbor)| neighbor.borrow() == other)
+            .map(|(edge_id, _)| *edge_id)
+    }
+
+    /// Returns `true` if there is an edge originating at `from` and
+    /// terminating at `to`.
+    pub fn contains_edge<Q>(&self, from: &Q, to: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find_edge(from, to).is_some()
+    }
+}
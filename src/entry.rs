@@ -0,0 +1,63 @@
+use std::hash::Hash;
+
+use dashmap::mapref::{entry::Entry as MapEntry, one::RefMut};
+
+/// A view into a single node in a [`Graph`](crate::Graph), which may or may
+/// not exist. Created with [`Graph::entry`](crate::Graph::entry).
+///
+/// The entry holds a write lock on its node's shard until it is consumed, so
+/// checking for a node and inserting it happens atomically. Unlike dashmap's
+/// entries, nodes cannot be removed through an [`Entry`], since that would
+/// leave their edges dangling. Use [`Graph::remove_node`](crate::Graph::remove_node)
+/// instead.
+pub struct Entry<'a, K, V> {
+    inner: MapEntry<'a, K, V>,
+}
+
+impl<'a, K: Eq + Hash, V> Entry<'a, K, V> {
+    pub(crate) fn new(inner: MapEntry<'a, K, V>) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the key of this entry.
+    pub fn key(&self) -> &K {
+        self.inner.key()
+    }
+
+    /// Returns `true` if the node is in the graph.
+    pub fn is_occupied(&self) -> bool {
+        matches!(self.inner, MapEntry::Occupied(_))
+    }
+
+    /// Modify the node's value if it is in the graph.
+    pub fn and_modify(self, f: impl FnOnce(&mut V)) -> Self {
+        Self::new(self.inner.and_modify(f))
+    }
+
+    /// Returns the node's value, inserting `value` if the node is not in the
+    /// graph.
+    pub fn or_insert(self, value: V) -> RefMut<'a, K, V> {
+        self.inner.or_insert(value)
+    }
+
+    /// Returns the node's value, inserting the result of `f` if the node is
+    /// not in the graph.
+    pub fn or_insert_with(self, f: impl FnOnce() -> V) -> RefMut<'a, K, V> {
+        self.inner.or_insert_with(f)
+    }
+
+    /// Returns the node's value, inserting [`V::default()`](Default::default)
+    /// if the node is not in the graph.
+    pub fn or_default(self) -> RefMut<'a, K, V>
+    where
+        V: Default,
+    {
+        self.inner.or_default()
+    }
+
+    /// Sets the node's value, inserting the node if it is not in the graph.
+    /// Edges of an existing node are kept.
+    pub fn insert(self, value: V) -> RefMut<'a, K, V> {
+        self.inner.insert(value)
+    }
+}
@@ -0,0 +1,199 @@
+//! Error types returned by fallible graph operations.
+use std::{
+    error::Error,
+    fmt::{self, Debug, Display},
+};
+
+use crate::EdgeId;
+
+/// An error produced when mutating a [`Graph`](crate::Graph).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphError<K> {
+    /// The node an edge originates from is not in the graph.
+    MissingSource(K),
+    /// The node an edge terminates at is not in the graph.
+    MissingTarget(K),
+}
+
+impl<K> GraphError<K> {
+    /// The key of the node that caused this error.
+    pub fn key(&self) -> &K {
+        match self {
+            Self::MissingSource(key) | Self::MissingTarget(key) => key,
+        }
+    }
+}
+
+impl<K: Debug> Display for GraphError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSource(key) => write!(f, "edge source {key:?} is not in the graph"),
+            Self::MissingTarget(key) => write!(f, "edge target {key:?} is not in the graph"),
+        }
+    }
+}
+
+impl<K: Debug> Error for GraphError<K> {}
+
+/// A reference in a graph's adjacency lists that cannot be resolved, or
+/// edge data that is not referenced at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dangling<K> {
+    /// An edge between `from` and `to` has no edge data.
+    Edge { edge_id: EdgeId, from: K, to: K },
+    /// An edge is incident to a node that is not in the graph.
+    Node { key: K, edge_id: EdgeId },
+    /// An edge is listed as an outgoing edge of `from` or an incoming edge of
+    /// `to`, but not both, or its own endpoints are not `from` and `to`.
+    Asymmetric { edge_id: EdgeId, from: K, to: K },
+    /// Edge data is stored for an edge that is not in any adjacency list.
+    Orphan { edge_id: EdgeId },
+}
+
+impl<K: Debug> Display for Dangling<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Edge { edge_id, from, to } => {
+                write!(f, "edge {edge_id:?} from {from:?} to {to:?} has no data")
+            }
+            Self::Node { key, edge_id } => {
+                write!(f, "edge {edge_id:?} refers to missing node {key:?}")
+            }
+            Self::Asymmetric { edge_id, from, to } => write!(
+                f,
+                "edge {edge_id:?} from {from:?} to {to:?} is missing from one adjacency list"
+            ),
+            Self::Orphan { edge_id } => {
+                write!(
+                    f,
+                    "edge {edge_id:?} has data but is not in any adjacency list"
+                )
+            }
+        }
+    }
+}
+
+/// A graph failed its referential integrity check.
+///
+/// Lists every [`Dangling`] reference found in the graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityError<K> {
+    pub(crate) dangling: Vec<Dangling<K>>,
+}
+
+impl<K> IntegrityError<K> {
+    /// Every dangling reference found in the graph.
+    pub fn dangling(&self) -> &[Dangling<K>] {
+        &self.dangling
+    }
+
+    /// Consume the error, returning every dangling reference found in the
+    /// graph.
+    pub fn into_dangling(self) -> Vec<Dangling<K>> {
+        self.dangling
+    }
+}
+
+impl<K: Debug> Display for IntegrityError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "graph has {} dangling reference(s)", self.dangling.len())?;
+        for dangling in &self.dangling {
+            write!(f, "\n  {dangling}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: Debug> Error for IntegrityError<K> {}
+
+/// A cycle that prevents an algorithm from completing, such as a cycle in a
+/// graph that was expected to be acyclic, or a cycle with negative total
+/// weight when searching for shortest paths.
+///
+/// The path lists each node on the cycle once, in edge order. The last node
+/// has an edge back to the first one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle<K> {
+    pub(crate) path: Vec<K>,
+}
+
+impl<K> Cycle<K> {
+    /// The nodes on the cycle, in edge order.
+    pub fn path(&self) -> &[K] {
+        &self.path
+    }
+
+    /// Consume the cycle, returning its nodes in edge order.
+    pub fn into_path(self) -> Vec<K> {
+        self.path
+    }
+}
+
+impl<K: Debug> Display for Cycle<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("graph contains a cycle: ")?;
+        for key in &self.path {
+            write!(f, "{key:?} -> ")?;
+        }
+        match self.path.first() {
+            Some(first) => write!(f, "{first:?}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<K: Debug> Error for Cycle<K> {}
+
+/// An error produced when writing or loading a [snapshot](crate::snapshot).
+#[cfg(feature = "snapshot")]
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Reading or writing the snapshot failed.
+    Io(std::io::Error),
+    /// The data does not start with the snapshot [magic bytes](crate::snapshot::MAGIC).
+    BadMagic,
+    /// The snapshot was written with a format version this crate can't read.
+    UnsupportedVersion(u32),
+    /// The snapshot ended before all of its data was read.
+    UnexpectedEof,
+    /// The snapshot is malformed.
+    Corrupt(&'static str),
+    /// An edge refers to a node or edge data that is not in the graph.
+    Dangling(EdgeId),
+    /// The graph has too many nodes to fit in a snapshot.
+    TooLarge,
+}
+
+#[cfg(feature = "snapshot")]
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "snapshot I/O failed: {err}"),
+            Self::BadMagic => f.write_str("data is not a graph snapshot"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {version}")
+            }
+            Self::UnexpectedEof => f.write_str("snapshot ended unexpectedly"),
+            Self::Corrupt(reason) => write!(f, "snapshot is corrupt: {reason}"),
+            Self::Dangling(edge_id) => write!(f, "edge {edge_id:?} is dangling"),
+            Self::TooLarge => f.write_str("graph has too many nodes for a snapshot"),
+        }
+    }
+}
+
+#[cfg(feature = "snapshot")]
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "snapshot")]
+impl From<std::io::Error> for SnapshotError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
@@ -4,12 +4,19 @@ use std::{
     hash::{BuildHasher, Hash},
 };
 
+#[cfg(feature = "rayon")]
+use dashmap::ReadOnlyView;
 use dashmap::{mapref::multiple::RefMulti, DashMap};
 
 use crate::Graph;
+#[cfg(feature = "rayon")]
+use crate::{EdgeId, ReadOnlyGraph};
 
 #[cfg(feature = "rayon")]
-use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator};
+use rayon::iter::{
+    plumbing::UnindexedConsumer, FromParallelIterator, IndexedParallelIterator,
+    IntoParallelIterator, IntoParallelRefIterator, ParallelExtend, ParallelIterator,
+};
 
 #[cfg(feature = "rayon")]
 impl<'a, K, V, E, S> Graph<K, V, E, S>
@@ -24,6 +31,205 @@ where
     ) -> impl rayon::iter::IntoParallelIterator<Item = RefMulti<'a, K, V>> + 'a {
         self.nodes.par_iter()
     }
+
+    /// Iterate over every edge in parallel, yielding its source, id, and
+    /// target.
+    ///
+    /// Each adjacency list is copied before its edges are yielded, so no locks
+    /// are held while items are processed and the graph may be written to
+    /// from inside the iterator. Reach edge data with [`Graph::get_edge`] or
+    /// [`Graph::update_edge`].
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    /// use rayon::prelude::*;
+    ///
+    /// let graph: Graph<u32, (), u32> = (0..3).map(|i| (i, ())).collect();
+    /// graph.add_edge(0, 1, 10);
+    /// graph.add_edge(0, 2, 20);
+    /// graph.add_edge(1, 2, 30);
+    ///
+    /// graph.par_iter_edges().for_each(|(_, edge_id, _)| {
+    ///     graph.update_edge(edge_id, |weight| *weight += 1);
+    /// });
+    /// let total: u32 = graph
+    ///     .par_iter_edges()
+    ///     .map(|(_, edge_id, _)| *graph.get_edge(edge_id).unwrap())
+    ///     .sum();
+    /// assert_eq!(total, 63);
+    /// ```
+    pub fn par_iter_edges(&'a self) -> impl ParallelIterator<Item = (K, EdgeId, K)> + 'a
+    where
+        K: Clone,
+    {
+        let sources: Vec<K> = self.from.iter().map(|list| list.key().clone()).collect();
+        sources.into_par_iter().flat_map_iter(move |from| {
+            let outgoing = self
+                .from
+                .get(&from)
+                .map(|list| list.clone())
+                .unwrap_or_default();
+            outgoing
+                .into_iter()
+                .map(move |(edge_id, to)| (from.clone(), edge_id, to))
+        })
+    }
+
+    /// Iterate over every node's outgoing adjacency list in parallel.
+    ///
+    /// Nodes that have never had an outgoing edge are skipped.
+    pub fn par_iter_adjacency(
+        &'a self,
+    ) -> impl ParallelIterator<Item = RefMulti<'a, K, Vec<(EdgeId, K)>>> + 'a {
+        self.from.par_iter()
+    }
+
+    /// Add many edges in parallel. Every edge is treated as in [`Graph::add_edge`].
+    ///
+    /// Edges get consecutive ids, in the order of `edges`, reserved all at
+    /// once. Both nodes of every edge are expected to exist, which is only
+    /// checked in debug builds.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    /// use rayon::prelude::*;
+    ///
+    /// let graph: Graph<u32, ()> = (0..1000).into_par_iter().map(|i| (i, ())).collect();
+    /// graph.par_add_edges((0..1000).into_par_iter().map(|i| (i, (i + 1) % 1000, ())));
+    /// assert_eq!(graph.edges_from(&999).unwrap().len(), 1);
+    /// ```
+    pub fn par_add_edges<I>(&self, edges: I)
+    where
+        I: IntoParallelIterator<Item = (K, K, E)>,
+        I::Iter: IndexedParallelIterator,
+        K: Clone,
+    {
+        let edges = edges.into_par_iter();
+        let start = self.reserve_edge_ids(edges.len() as u64);
+        edges.enumerate().for_each(|(i, (from, to, edge))| {
+            debug_assert!(self.nodes.contains_key(&from));
+            debug_assert!(self.nodes.contains_key(&to));
+            self.insert_edge(EdgeId(start + i as u64), from, to, edge);
+        });
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, K, V, E, S> ReadOnlyGraph<K, V, E, S>
+where
+    K: 'a + Eq + Hash + Send + Sync,
+    V: 'a + Send + Sync,
+    E: 'a + Send + Sync,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    /// Iterate over every edge in parallel, yielding its source, id, data,
+    /// and target. Edges without edge data are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    /// use rayon::prelude::*;
+    ///
+    /// let graph: Graph<u32, (), u32> = (0..3).map(|i| (i, ())).collect();
+    /// graph.add_edge(0, 1, 10);
+    /// graph.add_edge(1, 2, 30);
+    /// let graph = graph.into_read_only();
+    ///
+    /// let heavy: Vec<_> = graph
+    ///     .par_iter_edges()
+    ///     .filter(|(_, _, weight, _)| **weight > 20)
+    ///     .map(|(from, _, _, to)| (*from, *to))
+    ///     .collect();
+    /// assert_eq!(heavy, vec![(1, 2)]);
+    /// ```
+    pub fn par_iter_edges(&'a self) -> impl ParallelIterator<Item = (&'a K, EdgeId, &'a E, &'a K)> {
+        self.par_iter_adjacency()
+            .flat_map_iter(move |(from, outgoing)| {
+                outgoing.iter().filter_map(move |(edge_id, to)| {
+                    let edge = self.get_edge(*edge_id)?;
+                    Some((from, *edge_id, edge, to))
+                })
+            })
+    }
+
+    /// Iterate over every node's outgoing adjacency list in parallel.
+    ///
+    /// Nodes that have never had an outgoing edge are skipped.
+    pub fn par_iter_adjacency(
+        &'a self,
+    ) -> impl ParallelIterator<Item = (&'a K, &'a Vec<(EdgeId, K)>)> {
+        par_entries(&self.from)
+    }
+
+    /// Iterate over all nodes in parallel.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    /// use rayon::prelude::*;
+    ///
+    /// let graph: Graph<u32, u32> = (0..100).map(|i| (i, i * 2)).collect();
+    /// let graph = graph.into_read_only();
+    ///
+    /// let total: u32 = graph.par_iter_nodes().map(|(_, value)| *value).sum();
+    /// assert_eq!(total, 9900);
+    /// ```
+    pub fn par_iter_nodes(&'a self) -> ParNodes<'a, K, V, S> {
+        ParNodes { nodes: &self.nodes }
+    }
+
+    /// Iterate over every edge in parallel, yielding the values of its
+    /// endpoints and its data. Dangling edges are skipped. Use
+    /// [`ReadOnlyGraph::try_iter`] to find them.
+    pub fn par_iter(&'a self) -> impl ParallelIterator<Item = (&'a V, &'a E, &'a V)> {
+        self.par_iter_adjacency()
+            .filter_map(move |(key, edges)| self.nodes.get(key).map(|from| (from, edges)))
+            .flat_map_iter(move |(from, edges)| {
+                edges.iter().filter_map(move |(edge_id, to)| {
+                    Some((from, self.get_edge(*edge_id)?, self.nodes.get(to)?))
+                })
+            })
+    }
+}
+
+/// A parallel iterator over the nodes of a [`ReadOnlyGraph`], created with
+/// [`ReadOnlyGraph::par_iter_nodes`].
+#[cfg(feature = "rayon")]
+pub struct ParNodes<'a, K, V, S> {
+    nodes: &'a ReadOnlyView<K, V, S>,
+}
+
+#[cfg(feature = "rayon")]
+impl<'a, K, V, S> ParallelIterator for ParNodes<'a, K, V, S>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    type Item = (&'a K, &'a V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        par_entries(self.nodes).drive_unindexed(consumer)
+    }
+}
+
+/// Iterate over a read-only map in parallel, borrowing entries for the map's
+/// lifetime.
+#[cfg(feature = "rayon")]
+fn par_entries<K, V, S>(view: &ReadOnlyView<K, V, S>) -> impl ParallelIterator<Item = (&K, &V)>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    // dashmap only lends parallel entries for as long as a shard guard is
+    // held, but `ReadOnlyView::iter` borrows them for the map's lifetime.
+    view.iter().collect::<Vec<_>>().into_par_iter()
 }
 
 impl<K, V, E, S> FromIterator<(K, V)> for Graph<K, V, E, S>
@@ -62,6 +268,63 @@ where
     }
 }
 
+impl<K, V, E, S> Extend<(K, V)> for Graph<K, V, E, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, E, S> Extend<V> for Graph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    V: Borrow<K>,
+    S: BuildHasher + Clone,
+{
+    fn extend<T: IntoIterator<Item = V>>(&mut self, iter: T) {
+        for value in iter {
+            let key: K = value.borrow().clone();
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, E, S> Graph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    V: Default,
+    S: Default + BuildHasher + Clone,
+{
+    /// Create a graph from a list of `(from, to, edge)` edges.
+    ///
+    /// Nodes are created for every endpoint, with [`V::default()`](Default::default)
+    /// as their value.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, u32, f64> = Graph::from_edges([("a", "b", 1.0), ("b", "c", 2.0)]);
+    /// assert_eq!(graph.len(), 3);
+    /// assert_eq!(*graph.get_node(&"c").unwrap(), 0);
+    /// assert_eq!(graph.edges_from(&"a").unwrap().len(), 1);
+    /// ```
+    pub fn from_edges<T: IntoIterator<Item = (K, K, E)>>(edges: T) -> Self {
+        let graph = Graph::with_hasher(S::default());
+        for (from, to, edge) in edges {
+            graph.entry(from.clone()).or_default();
+            graph.entry(to.clone()).or_default();
+            graph.add_edge(from, to, edge);
+        }
+        graph
+    }
+}
+
 impl<'a, K, V, E, S> IntoIterator for &'a Graph<K, V, E, S>
 where
     K: 'a + Eq + Hash,
@@ -91,3 +354,77 @@ where
         IntoParallelIterator::into_par_iter(&self.nodes)
     }
 }
+
+#[cfg(feature = "rayon")]
+impl<'a, K, V, E, S> IntoParallelIterator for &'a ReadOnlyGraph<K, V, E, S>
+where
+    K: 'a + Eq + Hash + Send + Sync,
+    V: 'a + Send + Sync,
+    E: 'a + Send + Sync,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    type Item = (&'a K, &'a V);
+    type Iter = ParNodes<'a, K, V, S>;
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter_nodes()
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<K, V, E, S> FromParallelIterator<(K, V)> for Graph<K, V, E, S>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Send + Sync,
+    E: Send + Sync,
+    S: Default + BuildHasher + Clone + Send + Sync,
+{
+    fn from_par_iter<I: IntoParallelIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut graph = Graph::with_hasher(S::default());
+        graph.par_extend(iter);
+        graph
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<K, V, E, S> FromParallelIterator<V> for Graph<K, V, E, S>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    V: Borrow<K> + Send + Sync,
+    E: Send + Sync,
+    S: Default + BuildHasher + Clone + Send + Sync,
+{
+    fn from_par_iter<I: IntoParallelIterator<Item = V>>(iter: I) -> Self {
+        let mut graph = Graph::with_hasher(S::default());
+        graph.par_extend(iter);
+        graph
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<K, V, E, S> ParallelExtend<(K, V)> for Graph<K, V, E, S>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Send + Sync,
+    E: Send + Sync,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    fn par_extend<I: IntoParallelIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.nodes.par_extend(iter);
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<K, V, E, S> ParallelExtend<V> for Graph<K, V, E, S>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    V: Borrow<K> + Send + Sync,
+    E: Send + Sync,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    fn par_extend<I: IntoParallelIterator<Item = V>>(&mut self, iter: I) {
+        self.nodes.par_extend(
+            iter.into_par_iter()
+                .map(|value| (value.borrow().clone(), value)),
+        );
+    }
+}
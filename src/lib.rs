@@ -1,19 +1,33 @@
 #![doc = include_str!("../README.md")]
+pub mod algo;
+mod csr;
+mod edge_index;
+mod entry;
+mod error;
 mod iter;
+mod neighbors;
 mod read_only;
+mod scope;
 
 #[cfg(test)]
 mod test;
 
 #[cfg(feature = "dot")]
 pub mod dot;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "serde")]
+mod serde;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod visit;
 
 extern crate dashmap;
 extern crate nohash_hasher;
 
 use std::{
     borrow::Borrow,
-    collections::hash_map::RandomState,
+    collections::{hash_map::RandomState, HashMap, HashSet},
     fmt::{self, Debug},
     hash::{BuildHasher, Hash, Hasher},
     sync::atomic::AtomicU64,
@@ -22,12 +36,23 @@ use std::{
 use dashmap::{
     mapref::{
         multiple::RefMulti,
-        one::{Ref, RefMut},
+        one::{MappedRef, MappedRefMut, Ref, RefMut},
     },
     DashMap,
 };
+use edge_index::EdgeIndex;
 use nohash_hasher::{BuildNoHashHasher, IsEnabled};
-use read_only::ReadOnlyGraph;
+use scope::Access;
+
+pub use csr::CsrGraph;
+pub use entry::Entry;
+#[cfg(feature = "snapshot")]
+pub use error::SnapshotError;
+pub use error::{Cycle, Dangling, GraphError, IntegrityError};
+#[cfg(feature = "rayon")]
+pub use iter::ParNodes;
+pub use read_only::ReadOnlyGraph;
+pub use scope::Tracked;
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct EdgeId(u64);
@@ -39,6 +64,25 @@ impl Hash for EdgeId {
 
 impl IsEnabled for EdgeId {}
 
+/// The direction of an edge, relative to one of its endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Edges originating at a node.
+    Outgoing,
+    /// Edges terminating at a node.
+    Incoming,
+}
+
+impl Direction {
+    /// Returns the opposite direction.
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Outgoing => Self::Incoming,
+            Self::Incoming => Self::Outgoing,
+        }
+    }
+}
+
 type EdgeList<K, S> = DashMap<K, Vec<(EdgeId, K)>, S>;
 type EdgeHasher = BuildNoHashHasher<EdgeId>;
 type DefaultHasher = RandomState;
@@ -46,9 +90,13 @@ type DefaultHasher = RandomState;
 /// A thread-safe directed graph with stateful edges.
 pub struct Graph<K, V, E = (), S = DefaultHasher> {
     nodes: DashMap<K, V, S>,
-    edges: DashMap<EdgeId, E, EdgeHasher>,
+    /// The source, data, and target of every edge.
+    edges: DashMap<EdgeId, (K, E, K), EdgeHasher>,
     to: EdgeList<K, S>,
     from: EdgeList<K, S>,
+    /// Optional index of edges by source and target. See
+    /// [`Graph::enable_edge_index`].
+    edge_index: Option<EdgeIndex<K, S>>,
     curr_edge_id: AtomicU64,
 }
 
@@ -63,6 +111,7 @@ where
             edges: DashMap::with_hasher(EdgeHasher::default()),
             to: DashMap::default(),
             from: DashMap::default(),
+            edge_index: None,
             curr_edge_id: AtomicU64::new(0),
         }
     }
@@ -72,7 +121,7 @@ impl<'a, K: 'a + Eq + Hash, V: 'a, E: 'a> Graph<K, V, E, DefaultHasher> {
     ///
     /// # Example
     /// ```
-    /// use weave::Graph;
+    /// use carpet::Graph;
     /// let graph: Graph<u64, String> = Graph::new();
     /// assert!(graph.is_empty());
     /// ```
@@ -84,7 +133,7 @@ impl<'a, K: 'a + Eq + Hash, V: 'a, E: 'a> Graph<K, V, E, DefaultHasher> {
     ///
     /// # Example
     /// ```
-    /// use weave::Graph;
+    /// use carpet::Graph;
     ///
     /// let graph: Graph<u64, String> = Graph::with_capacity(10);
     /// assert!(graph.is_empty());
@@ -115,7 +164,7 @@ where
     /// # Example
     /// ```
     /// use nohash_hasher::BuildNoHashHasher;
-    /// use weave::Graph;
+    /// use carpet::Graph;
     ///
     /// let graph: Graph<u64, u64, (), BuildNoHashHasher<u64>> = Graph::with_capacity_and_hasher(10, Default::default());
     /// assert!(graph.is_empty());
@@ -129,6 +178,7 @@ where
             edges: DashMap::with_capacity_and_hasher(capacity, EdgeHasher::default()),
             to: DashMap::with_capacity_and_hasher(edge_capacity, hasher.clone()),
             from: DashMap::with_capacity_and_hasher(edge_capacity, hasher),
+            edge_index: None,
             curr_edge_id: AtomicU64::new(0),
         }
     }
@@ -148,67 +198,325 @@ where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
+        scope::check(&self.nodes, key, Access::Read, "node");
         self.nodes.contains_key(key)
     }
 
-    pub fn get_node<Q>(&'a self, key: &Q) -> Option<Ref<'a, K, V>>
+    pub fn get_node<Q>(&'a self, key: &Q) -> Option<Tracked<Ref<'a, K, V>>>
     where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        self.nodes.get(key)
+        let held = scope::hold(&self.nodes, key, Access::Read, "node");
+        Some(Tracked::new(self.nodes.get(key)?, held))
     }
 
-    pub fn get_node_mut<Q>(&'a self, key: &Q) -> Option<RefMut<'a, K, V>>
+    pub fn get_node_mut<Q>(&'a self, key: &Q) -> Option<Tracked<RefMut<'a, K, V>>>
     where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        self.nodes.get_mut(key)
+        let held = scope::hold(&self.nodes, key, Access::Write, "node");
+        Some(Tracked::new(self.nodes.get_mut(key)?, held))
+    }
+
+    /// Get the node's [`Entry`] for in-place insertion or modification.
+    ///
+    /// The entry holds a write lock on the node's shard until it is dropped,
+    /// so other threads cannot insert the same node in the meantime.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, u32> = Graph::new();
+    /// for word in ["a", "b", "a"] {
+    ///     graph.entry(word).and_modify(|count| *count += 1).or_insert(1);
+    /// }
+    /// assert_eq!(*graph.get_node(&"a").unwrap(), 2);
+    /// assert_eq!(*graph.get_node(&"b").unwrap(), 1);
+    /// ```
+    pub fn entry(&'a self, key: K) -> Entry<'a, K, V> {
+        scope::check(&self.nodes, &key, Access::Write, "node");
+        Entry::new(self.nodes.entry(key))
     }
 
     /// Inserts a node into the graph under the given `key`. Returns the old value associated with the key if there was one.
     pub fn insert(&self, key: K, value: V) -> Option<V> {
+        scope::check(&self.nodes, &key, Access::Write, "node");
         self.nodes.insert(key, value)
     }
 
-    pub fn get_edge(&'a self, edge_id: EdgeId) -> Option<Ref<'a, EdgeId, E>> {
-        self.edges.get(&edge_id)
+    /// Removes a node from the graph, returning its value if it was present.
+    ///
+    /// All edges incident to the node are removed as well, and the node is
+    /// scrubbed from the adjacency lists of its neighbors.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<u64, &str> = [(1, "a"), (2, "b")].into_iter().collect();
+    /// graph.add_edge(1, 2, ());
+    ///
+    /// assert_eq!(graph.remove_node(&2), Some("b"));
+    /// assert!(!graph.has_node(&2));
+    /// assert!(graph.edges_from(&1).unwrap().is_empty());
+    /// ```
+    pub fn remove_node<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        scope::check(&self.nodes, key, Access::Write, "node");
+        let (_, value) = self.nodes.remove(key)?;
+
+        // Outgoing edges terminate at neighbors whose `to` lists must be updated.
+        scope::check(&self.from, key, Access::Write, "outgoing edge");
+        if let Some((_, outgoing)) = self.from.remove(key) {
+            for (edge_id, to) in outgoing {
+                scope::check(&self.edges, &edge_id, Access::Write, "edge");
+                self.edges.remove(&edge_id);
+                scope::check::<K, _, _, K>(&self.to, &to, Access::Write, "incoming edge");
+                if let Some(mut incoming) = self.to.get_mut::<K>(&to) {
+                    incoming.retain(|(id, _)| *id != edge_id);
+                }
+            }
+        }
+
+        // Likewise, incoming edges originate at neighbors with `from` lists.
+        scope::check(&self.to, key, Access::Write, "incoming edge");
+        let incoming = self.to.remove(key).map(|(_, incoming)| incoming);
+        self.unindex_node(key, incoming.iter().flatten().map(|(_, from)| from));
+        for (edge_id, from) in incoming.into_iter().flatten() {
+            scope::check(&self.edges, &edge_id, Access::Write, "edge");
+            self.edges.remove(&edge_id);
+            scope::check::<K, _, _, K>(&self.from, &from, Access::Write, "outgoing edge");
+            if let Some(mut outgoing) = self.from.get_mut::<K>(&from) {
+                outgoing.retain(|(id, _)| *id != edge_id);
+            }
+        }
+
+        Some(value)
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn get_edge(
+        &'a self,
+        edge_id: EdgeId,
+    ) -> Option<Tracked<MappedRef<'a, EdgeId, (K, E, K), E>>> {
+        let held = scope::hold(&self.edges, &edge_id, Access::Read, "edge");
+        let edge = self.edges.get(&edge_id)?.map(|(_, edge, _)| edge);
+        Some(Tracked::new(edge, held))
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn get_edge_mut(
+        &'a self,
+        edge_id: EdgeId,
+    ) -> Option<Tracked<MappedRefMut<'a, EdgeId, (K, E, K), E>>> {
+        let held = scope::hold(&self.edges, &edge_id, Access::Write, "edge");
+        let edge = self.edges.get_mut(&edge_id)?.map(|(_, edge, _)| edge);
+        Some(Tracked::new(edge, held))
+    }
+
+    /// Modify an edge's data in place, returning the result of `f`. Returns
+    /// `None` if the edge is not in the graph.
+    ///
+    /// The edge is locked while `f` runs, so concurrent updates to the same
+    /// edge never race.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, (), u32> = [("a", ()), ("b", ())].into_iter().collect();
+    /// let edge_id = graph.add_edge("a", "b", 0);
+    ///
+    /// graph.update_edge(edge_id, |count| *count += 1);
+    /// assert_eq!(graph.update_edge(edge_id, |count| *count), Some(1));
+    /// ```
+    pub fn update_edge<R, F>(&'a self, edge_id: EdgeId, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut E) -> R,
+    {
+        scope::check(&self.edges, &edge_id, Access::Write, "edge");
+        self.edges
+            .get_mut(&edge_id)
+            .map(|mut edge| f(&mut edge.value_mut().1))
     }
 
     /// Add an edge between two existing nodes, originating at `from` and terminating at `to`.
-    pub fn add_edge(&self, from: K, to: K, edge: E)
+    ///
+    /// Returns the [`EdgeId`] of the new edge. Both nodes are expected to exist, which is only
+    /// checked in debug builds. Use [`Graph::try_add_edge`] when that is not guaranteed.
+    pub fn add_edge(&self, from: K, to: K, edge: E) -> EdgeId
     where
         K: Clone,
         // V: Default,
-        S: Default,
     {
         debug_assert!(self.nodes.contains_key(&from));
         debug_assert!(self.nodes.contains_key(&to));
         let edge_id = self.next_edge_id();
-        self.edges.insert(edge_id, edge);
+        self.insert_edge(edge_id, from, to, edge);
+        edge_id
+    }
 
+    /// Insert an edge with an already-reserved id.
+    pub(crate) fn insert_edge(&self, edge_id: EdgeId, from: K, to: K, edge: E)
+    where
+        K: Clone,
+    {
+        scope::check(&self.from, &from, Access::Write, "outgoing edge");
+        scope::check(&self.to, &to, Access::Write, "incoming edge");
         self.from
             .entry(from.clone())
             .or_default()
             .push((edge_id, to.clone()));
-        self.to.entry(to).or_default().push((edge_id, from));
+        self.index_edge(edge_id, &from, &to);
+        self.to
+            .entry(to.clone())
+            .or_default()
+            .push((edge_id, from.clone()));
+
+        // The edge goes in last, so a concurrent `remove_edge` either does not
+        // find it, or finds it in both adjacency lists.
+        scope::check(&self.edges, &edge_id, Access::Write, "edge");
+        self.edges.insert(edge_id, (from, edge, to));
     }
 
-    pub fn edges_from<Q>(&'a self, from: &Q) -> Option<Ref<'a, K, Vec<(EdgeId, K)>>>
+    /// Add an edge between two nodes, failing if either of them is not in the graph.
+    ///
+    /// Note that a node may still be removed by another thread after this check passes.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::{Graph, GraphError};
+    ///
+    /// let graph: Graph<u64, &str> = [(1, "a"), (2, "b")].into_iter().collect();
+    /// let edge_id = graph.try_add_edge(1, 2, ()).unwrap();
+    /// assert!(graph.get_edge(edge_id).is_some());
+    ///
+    /// assert_eq!(graph.try_add_edge(1, 3, ()), Err(GraphError::MissingTarget(3)));
+    /// ```
+    pub fn try_add_edge(&self, from: K, to: K, edge: E) -> Result<EdgeId, GraphError<K>>
+    where
+        K: Clone,
+    {
+        if !self.nodes.contains_key(&from) {
+            return Err(GraphError::MissingSource(from));
+        }
+        if !self.nodes.contains_key(&to) {
+            return Err(GraphError::MissingTarget(to));
+        }
+        Ok(self.add_edge(from, to, edge))
+    }
+
+    /// Removes an edge from the graph, returning its source, data, and target.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<u64, &str, &str> = [(1, "a"), (2, "b")].into_iter().collect();
+    /// let edge_id = graph.add_edge(1, 2, "follows");
+    ///
+    /// assert_eq!(graph.remove_edge(edge_id), Some((1, "follows", 2)));
+    /// assert!(graph.get_edge(edge_id).is_none());
+    /// assert!(graph.edges_to(&2).unwrap().is_empty());
+    /// ```
+    pub fn remove_edge(&self, edge_id: EdgeId) -> Option<(K, E, K)> {
+        // Removing the edge first claims it, along with its endpoints, so
+        // concurrent removals will not both try to clean up adjacency lists.
+        scope::check(&self.edges, &edge_id, Access::Write, "edge");
+        let (_, (from, edge, to)) = self.edges.remove(&edge_id)?;
+        self.unindex_edge(edge_id, &from, &to);
+
+        scope::check(&self.from, &from, Access::Write, "outgoing edge");
+        if let Some(mut outgoing) = self.from.get_mut(&from) {
+            if let Some(i) = outgoing.iter().position(|(id, _)| *id == edge_id) {
+                outgoing.remove(i);
+            }
+        }
+        scope::check(&self.to, &to, Access::Write, "incoming edge");
+        if let Some(mut incoming) = self.to.get_mut(&to) {
+            if let Some(i) = incoming.iter().position(|(id, _)| *id == edge_id) {
+                incoming.remove(i);
+            }
+        }
+
+        Some((from, edge, to))
+    }
+
+    /// Returns the source and target of an edge, or `None` if the edge is not
+    /// in the graph.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, ()> = [("a", ()), ("b", ())].into_iter().collect();
+    /// let edge_id = graph.add_edge("a", "b", ());
+    ///
+    /// assert_eq!(graph.edge_endpoints(edge_id), Some(("a", "b")));
+    /// graph.remove_edge(edge_id);
+    /// assert_eq!(graph.edge_endpoints(edge_id), None);
+    /// ```
+    pub fn edge_endpoints(&self, edge_id: EdgeId) -> Option<(K, K)>
+    where
+        K: Clone,
+    {
+        self.edges
+            .get(&edge_id)
+            .map(|edge| (edge.0.clone(), edge.2.clone()))
+    }
+
+    /// Returns the id and opposite endpoint of every edge incident to `key` in
+    /// the given `direction`.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::{Direction, Graph};
+    ///
+    /// let graph: Graph<&str, ()> = [("a", ()), ("b", ())].into_iter().collect();
+    /// let edge_id = graph.add_edge("a", "b", ());
+    ///
+    /// assert_eq!(graph.edges_directed("b", Direction::Incoming).unwrap()[0], (edge_id, "a"));
+    /// assert!(graph.edges_directed("b", Direction::Outgoing).is_none());
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn edges_directed<Q>(
+        &'a self,
+        key: &Q,
+        direction: Direction,
+    ) -> Option<Tracked<Ref<'a, K, Vec<(EdgeId, K)>>>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (adjacency, what) = self.adjacency(direction);
+        let held = scope::hold(adjacency, key, Access::Read, what);
+        Some(Tracked::new(adjacency.get(key)?, held))
+    }
+
+    /// Shorthand for [`Graph::edges_directed`] with [`Direction::Outgoing`].
+    #[allow(clippy::type_complexity)]
+    pub fn edges_from<Q>(&'a self, from: &Q) -> Option<Tracked<Ref<'a, K, Vec<(EdgeId, K)>>>>
     where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        self.from.get(from)
+        self.edges_directed(from, Direction::Outgoing)
     }
 
-    pub fn edges_to<Q>(&'a self, to: &Q) -> Option<Ref<'a, K, Vec<(EdgeId, K)>>>
+    /// Shorthand for [`Graph::edges_directed`] with [`Direction::Incoming`].
+    #[allow(clippy::type_complexity)]
+    pub fn edges_to<Q>(&'a self, to: &Q) -> Option<Tracked<Ref<'a, K, Vec<(EdgeId, K)>>>>
     where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        self.to.get(to)
+        self.edges_directed(to, Direction::Incoming)
     }
 
     pub fn iter_nodes(&'a self) -> impl Iterator<Item = RefMulti<'a, K, V>> + 'a {
@@ -224,6 +532,7 @@ where
         self.edges.shrink_to_fit();
         self.to.shrink_to_fit();
         self.from.shrink_to_fit();
+        self.shrink_edge_index(false);
     }
 
     /// Aggressively release unused memory resources.
@@ -241,6 +550,120 @@ where
             from.shrink_to_fit();
         }
         self.from.shrink_to_fit();
+        self.shrink_edge_index(true);
+    }
+
+    /// Check that every edge in the graph has edge data, connects two existing nodes, and is listed
+    /// in the adjacency lists of both of them. Edge data that no adjacency list refers to is
+    /// reported as well.
+    ///
+    /// Graphs can only end up with dangling references when edges are added between missing
+    /// nodes via [`Graph::add_edge`], or when nodes and edges are removed while other threads are
+    /// adding edges to them. Concurrent writes may also be reported while they are in progress.
+    pub fn check_integrity(&self) -> Result<(), IntegrityError<K>>
+    where
+        K: Clone,
+    {
+        let mut dangling = Vec::new();
+        let mut seen: HashSet<EdgeId, EdgeHasher> = HashSet::default();
+
+        let check = |edge_id: EdgeId, from: &K, to: &K, dangling: &mut Vec<Dangling<K>>| {
+            match self.edges.get(&edge_id) {
+                None => dangling.push(Dangling::Edge {
+                    edge_id,
+                    from: from.clone(),
+                    to: to.clone(),
+                }),
+                // Adjacency lists that disagree with the edge's own endpoints
+                Some(edge) if edge.0 != *from || edge.2 != *to => {
+                    dangling.push(Dangling::Asymmetric {
+                        edge_id,
+                        from: from.clone(),
+                        to: to.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+            for key in [from, to] {
+                if !self.nodes.contains_key(key) {
+                    dangling.push(Dangling::Node {
+                        key: key.clone(),
+                        edge_id,
+                    });
+                }
+            }
+        };
+
+        // Outgoing edges that have not been matched with an incoming edge yet
+        let mut unmatched: HashMap<EdgeId, (K, K), EdgeHasher> = HashMap::default();
+        for outgoing in self.from.iter() {
+            for (edge_id, to) in outgoing.value() {
+                seen.insert(*edge_id);
+                unmatched.insert(*edge_id, (outgoing.key().clone(), to.clone()));
+                check(*edge_id, outgoing.key(), to, &mut dangling);
+            }
+        }
+        for incoming in self.to.iter() {
+            for (edge_id, from) in incoming.value() {
+                match unmatched.remove(edge_id) {
+                    Some(endpoints) if endpoints.0 == *from && endpoints.1 == *incoming.key() => {}
+                    Some((from, to)) => dangling.push(Dangling::Asymmetric {
+                        edge_id: *edge_id,
+                        from,
+                        to,
+                    }),
+                    // Edges only listed as incoming edges
+                    None => {
+                        dangling.push(Dangling::Asymmetric {
+                            edge_id: *edge_id,
+                            from: from.clone(),
+                            to: incoming.key().clone(),
+                        });
+                        if seen.insert(*edge_id) {
+                            check(*edge_id, from, incoming.key(), &mut dangling);
+                        }
+                    }
+                }
+            }
+        }
+        // Edges only listed as outgoing edges
+        for (edge_id, (from, to)) in unmatched {
+            dangling.push(Dangling::Asymmetric { edge_id, from, to });
+        }
+
+        for edge in self.edges.iter() {
+            if !seen.contains(edge.key()) {
+                dangling.push(Dangling::Orphan {
+                    edge_id: *edge.key(),
+                });
+            }
+        }
+
+        if dangling.is_empty() {
+            Ok(())
+        } else {
+            Err(IntegrityError { dangling })
+        }
+    }
+
+    /// Create a [`ReadOnlyGraph`] after checking the graph's [integrity](Graph::check_integrity).
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<u64, &str> = [(1, "a"), (2, "b")].into_iter().collect();
+    /// graph.add_edge(1, 2, ());
+    ///
+    /// let graph = graph.try_into_read_only().unwrap();
+    /// assert_eq!(graph.iter().count(), 1);
+    /// ```
+    pub fn try_into_read_only(self) -> Result<ReadOnlyGraph<K, V, E, S>, IntegrityError<K>>
+    where
+        K: Clone,
+    {
+        self.check_integrity()?;
+        Ok(self.into_read_only())
     }
 
     pub fn into_read_only(self) -> ReadOnlyGraph<K, V, E, S> {
@@ -249,6 +672,8 @@ where
             edges: self.edges.into_read_only(),
             to: self.to.into_read_only(),
             from: self.from.into_read_only(),
+            edge_index: self.edge_index,
+            next_edge_id: self.curr_edge_id.into_inner(),
         }
     }
 }
@@ -266,6 +691,7 @@ where
             edges: self.edges.clone(),
             to: self.to.clone(),
             from: self.from.clone(),
+            edge_index: self.edge_index.clone(),
             curr_edge_id: AtomicU64::new(
                 self.curr_edge_id.load(std::sync::atomic::Ordering::Relaxed),
             ),
@@ -274,12 +700,28 @@ where
 }
 
 impl<K, V, E, S> Graph<K, V, E, S> {
+    /// Returns the adjacency lists for `direction`, and their name for the
+    /// deadlock detector.
+    fn adjacency(&self, direction: Direction) -> (&EdgeList<K, S>, &'static str) {
+        match direction {
+            Direction::Outgoing => (&self.from, "outgoing edge"),
+            Direction::Incoming => (&self.to, "incoming edge"),
+        }
+    }
+
     pub(self) fn next_edge_id(&self) -> EdgeId {
         EdgeId(
             self.curr_edge_id
                 .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
         )
     }
+
+    /// Reserve `count` consecutive edge ids, returning the first one.
+    #[cfg(feature = "rayon")]
+    pub(self) fn reserve_edge_ids(&self, count: u64) -> u64 {
+        self.curr_edge_id
+            .fetch_add(count, std::sync::atomic::Ordering::Relaxed)
+    }
 }
 
 #[cfg(feature = "dot")]
@@ -0,0 +1,505 @@
+//! Memory-mapped graphs, enabled by the `mmap` feature.
+//!
+//! A [`MappedGraph`] is a frozen graph that is queried directly from a
+//! memory-mapped file, without deserializing it first. Node keys, node
+//! values, and edge data must be plain-old-data ([`Pod`]), so they can be read
+//! straight out of the mapped pages. Since the pages are backed by the file,
+//! several processes mapping the same file share a single copy of the graph.
+//!
+//! ```
+//! use carpet::{mmap::MappedGraph, Graph};
+//!
+//! let graph: Graph<u32, f32, u64> = (0..3).map(|i| (i, i as f32)).collect();
+//! let edge = graph.add_edge(0, 1, 42);
+//! graph.add_edge(0, 2, 7);
+//!
+//! let name = format!("carpet-mmap-doctest-{}.bin", std::process::id());
+//! let path = std::env::temp_dir().join(name);
+//! let file = std::fs::File::create(&path).unwrap();
+//! graph.into_read_only().write_mapped(std::io::BufWriter::new(file)).unwrap();
+//!
+//! // SAFETY: nothing else modifies the file while it is mapped.
+//! let mapped = unsafe { MappedGraph::<u32, f32, u64>::open(&path) }.unwrap();
+//! mapped.validate().unwrap();
+//! assert_eq!(mapped.get_node(&2), Some(&2.0));
+//! assert_eq!(mapped.get_edge(edge), Some(&42));
+//! assert_eq!(mapped.edge_ids_from(&0).unwrap().count(), 2);
+//! # std::fs::remove_file(path).unwrap();
+//! ```
+//!
+//! # Format
+//!
+//! Mapped graphs use a different file format than [snapshots](crate::snapshot),
+//! since every value must be stored in place. Header fields are little-endian,
+//! but every section is stored in the native byte order of the machine that
+//! wrote it. Files can only be read on machines with the same byte order and
+//! type layouts, and files from other machines are rejected.
+//!
+//! The file starts with a 64 byte header: the [`MAGIC`] bytes, the format
+//! [`VERSION`] as a `u32`, a `u32` byte order mark in native byte order, the
+//! number of nodes `n` and edges `m` as `u64`s, and the sizes of `K`, `V`, and
+//! `E` as `u32`s. It is followed by these sections, each starting at a
+//! multiple of 64 bytes:
+//!
+//! 1. `n` keys, sorted in ascending order.
+//! 2. `n` values, in the same order as the keys.
+//! 3. `n + 1` `u64` offsets into the edge arrays. The outgoing edges of node `i`
+//!    are at `offsets[i]..offsets[i + 1]`.
+//! 4. `m` `u64` [`EdgeId`]s.
+//! 5. `m` `u32` target node indices.
+//! 6. `m` edge data values.
+//! 7. `m` `u64` [`EdgeId`]s, sorted in ascending order.
+//! 8. `m` `u64` positions in the edge arrays, in the same order as the sorted
+//!    edge ids.
+use std::{
+    fmt::{self, Debug},
+    fs::File,
+    hash::{BuildHasher, Hash},
+    io::Write,
+    marker::PhantomData,
+    mem,
+    ops::Range,
+    path::Path,
+};
+
+pub use bytemuck::Pod;
+use memmap2::Mmap;
+use rayon::prelude::*;
+
+use crate::{EdgeId, ReadOnlyGraph, SnapshotError};
+
+/// The first bytes of every mapped graph file.
+pub const MAGIC: [u8; 8] = *b"CARPETMM";
+
+/// The current version of the mapped graph format.
+pub const VERSION: u32 = 1;
+
+/// Written in native byte order, so it reads differently on machines with the
+/// opposite byte order.
+const BYTE_ORDER_MARK: u32 = 0x0102_0304;
+
+/// Alignment of the header and every section.
+const ALIGN: usize = 64;
+
+/// Round `offset` up to the next section boundary.
+fn align(offset: usize) -> usize {
+    offset.div_ceil(ALIGN) * ALIGN
+}
+
+/// Byte ranges of each section in a mapped graph file.
+#[derive(Debug, Clone, Default)]
+struct Sections {
+    keys: Range<usize>,
+    values: Range<usize>,
+    edge_offsets: Range<usize>,
+    edge_ids: Range<usize>,
+    targets: Range<usize>,
+    edges: Range<usize>,
+    sorted_edge_ids: Range<usize>,
+    edge_positions: Range<usize>,
+}
+
+impl Sections {
+    /// Lay out the sections of a file with `node_count` nodes and `edge_count` edges.
+    fn new<K, V, E>(node_count: usize, edge_count: usize) -> Option<Self> {
+        let mut end = ALIGN;
+        let mut next = |len: usize, size: usize| -> Option<Range<usize>> {
+            let start = align(end);
+            end = start.checked_add(len.checked_mul(size)?)?;
+            Some(start..end)
+        };
+        Some(Self {
+            keys: next(node_count, mem::size_of::<K>())?,
+            values: next(node_count, mem::size_of::<V>())?,
+            edge_offsets: next(node_count.checked_add(1)?, 8)?,
+            edge_ids: next(edge_count, 8)?,
+            targets: next(edge_count, 4)?,
+            edges: next(edge_count, mem::size_of::<E>())?,
+            sorted_edge_ids: next(edge_count, 8)?,
+            edge_positions: next(edge_count, 8)?,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.edge_positions.end
+    }
+}
+
+/// A frozen graph queried directly from a memory-mapped file. See the
+/// [module documentation](self) for details.
+pub struct MappedGraph<K, V, E> {
+    map: Mmap,
+    node_count: usize,
+    edge_count: usize,
+    sections: Sections,
+    _marker: PhantomData<(K, V, E)>,
+}
+
+impl<K: Pod + Ord + Sync, V: Pod + Sync, E: Pod + Sync> MappedGraph<K, V, E> {
+    /// Map the graph stored in the file at `path`.
+    ///
+    /// Only the header and edge offsets are checked. Use
+    /// [`MappedGraph::validate`] to check the rest of the file.
+    ///
+    /// # Safety
+    /// The file must not be modified or truncated, by this or any other
+    /// process, while the graph is alive. See [`Mmap::map`].
+    ///
+    /// # Errors
+    /// Fails if the file cannot be opened or mapped, or if it is not a valid
+    /// mapped graph with the same `K`, `V`, and `E` layouts.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        let file = File::open(path)?;
+        // SAFETY: upheld by the caller. The map is only ever read, and
+        // sections are read through bounds-checked slices.
+        let map = unsafe { Mmap::map(&file)? };
+        Self::from_mmap(map)
+    }
+
+    /// Use a file that has already been mapped. Like [`MappedGraph::open`],
+    /// this only checks the header and edge offsets.
+    ///
+    /// # Errors
+    /// Fails if `map` is not a valid mapped graph with the same `K`, `V`, and
+    /// `E` layouts.
+    pub fn from_mmap(map: Mmap) -> Result<Self, SnapshotError> {
+        if mem::align_of::<K>() > ALIGN
+            || mem::align_of::<V>() > ALIGN
+            || mem::align_of::<E>() > ALIGN
+        {
+            return Err(SnapshotError::Corrupt("type alignment is too large"));
+        }
+
+        let header = map.get(..ALIGN).ok_or(SnapshotError::UnexpectedEof)?;
+        if header[..8] != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = read_u32(header, 8);
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        if header[12..16] != BYTE_ORDER_MARK.to_ne_bytes() {
+            return Err(SnapshotError::Corrupt(
+                "file was written on a machine with a different byte order",
+            ));
+        }
+        let node_count = read_u64(header, 16) as usize;
+        let edge_count = read_u64(header, 24) as usize;
+        let sizes = [
+            read_u32(header, 32),
+            read_u32(header, 36),
+            read_u32(header, 40),
+        ];
+        if sizes != [size_of::<K>(), size_of::<V>(), size_of::<E>()] {
+            return Err(SnapshotError::Corrupt("type sizes do not match"));
+        }
+        if node_count > u32::MAX as usize {
+            return Err(SnapshotError::Corrupt("too many nodes"));
+        }
+
+        let sections = Sections::new::<K, V, E>(node_count, edge_count)
+            .ok_or(SnapshotError::Corrupt("section length overflow"))?;
+        if map.len() < sections.len() {
+            return Err(SnapshotError::UnexpectedEof);
+        }
+        let graph = Self {
+            map,
+            node_count,
+            edge_count,
+            sections,
+            _marker: PhantomData,
+        };
+        let offsets = graph.edge_offsets();
+        if offsets[0] != 0 || offsets[node_count] != edge_count as u64 {
+            return Err(SnapshotError::Corrupt("edge offsets out of bounds"));
+        }
+        if offsets.par_windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(SnapshotError::Corrupt("edge offsets are not sorted"));
+        }
+        Ok(graph)
+    }
+
+    /// Check every section of the file, not just its header and edge offsets.
+    ///
+    /// Opening a graph only reads what is needed to lay out its sections, so
+    /// startup does not fault in the whole file. Queries on a corrupt file
+    /// that has not been validated may panic or miss nodes and edges, but are
+    /// never unsound. This scans the whole file, in parallel.
+    ///
+    /// # Errors
+    /// Fails if an edge target or position is out of bounds, or if the keys or
+    /// sorted edge ids are not in ascending order.
+    pub fn validate(&self) -> Result<(), SnapshotError> {
+        let node_count = self.node_count as u32;
+        if self
+            .targets()
+            .par_iter()
+            .any(|target| *target >= node_count)
+        {
+            return Err(SnapshotError::Corrupt("edge target out of bounds"));
+        }
+        let edge_count = self.edge_count as u64;
+        if self
+            .edge_positions()
+            .par_iter()
+            .any(|position| *position >= edge_count)
+        {
+            return Err(SnapshotError::Corrupt("edge position out of bounds"));
+        }
+        if (0..self.node_count.saturating_sub(1))
+            .into_par_iter()
+            .any(|i| self.key(i) >= self.key(i + 1))
+        {
+            return Err(SnapshotError::Corrupt("keys are not sorted"));
+        }
+        if self
+            .sorted_edge_ids()
+            .par_windows(2)
+            .any(|pair| pair[0] >= pair[1])
+        {
+            return Err(SnapshotError::Corrupt("edge ids are not sorted"));
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node_count == 0
+    }
+
+    /// Returns the number of nodes in the graph.
+    pub fn len(&self) -> usize {
+        self.node_count
+    }
+
+    /// Returns the number of edges in the graph.
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    pub fn has_node(&self, key: &K) -> bool {
+        self.index_of(key).is_some()
+    }
+
+    pub fn get_node(&self, key: &K) -> Option<&V> {
+        self.index_of(key).map(|i| self.value(i))
+    }
+
+    pub fn get_edge(&self, edge_id: EdgeId) -> Option<&E> {
+        let position = self.sorted_edge_ids().binary_search(&edge_id.0).ok()?;
+        Some(self.edge(self.edge_positions()[position] as usize))
+    }
+
+    /// Returns the id and target of every edge originating from `key`, or
+    /// `None` if `key` is not in the graph.
+    pub fn edge_ids_from(&self, key: &K) -> Option<impl Iterator<Item = (EdgeId, &K)> + '_> {
+        let edges = self.edge_range(self.index_of(key)?);
+        let edge_ids = &self.edge_ids()[edges.clone()];
+        let targets = &self.targets()[edges];
+        Some(
+            edge_ids
+                .iter()
+                .zip(targets)
+                .map(|(edge_id, target)| (EdgeId(*edge_id), self.key(*target as usize))),
+        )
+    }
+
+    /// Returns the source value, edge data, and target value of every edge
+    /// originating from `key`, or `None` if `key` is not in the graph.
+    pub fn iter_edges_from(&self, key: &K) -> Option<impl Iterator<Item = (&V, &E, &V)> + '_> {
+        let from = self.index_of(key)?;
+        let edges = self.edge_range(from);
+        let targets = &self.targets()[edges.clone()];
+        Some(
+            edges
+                .zip(targets)
+                .map(move |(e, to)| (self.value(from), self.edge(e), self.value(*to as usize))),
+        )
+    }
+
+    /// Iterate over all nodes in the graph, in ascending key order.
+    pub fn iter_nodes(&self) -> impl Iterator<Item = (&K, &V)> {
+        (0..self.node_count).map(|i| (self.key(i), self.value(i)))
+    }
+
+    /// Iterate over all edges in the graph, grouped by source node.
+    pub fn iter(&self) -> impl Iterator<Item = (&V, &E, &V)> {
+        (0..self.node_count).flat_map(move |from| {
+            let edges = self.edge_range(from);
+            let targets = &self.targets()[edges.clone()];
+            edges
+                .zip(targets)
+                .map(move |(e, to)| (self.value(from), self.edge(e), self.value(*to as usize)))
+        })
+    }
+
+    fn index_of(&self, key: &K) -> Option<usize> {
+        // Keys aren't read as a slice so zero-sized keys work too
+        let (mut low, mut high) = (0, self.node_count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.key(mid).cmp(key) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    fn edge_range(&self, node: usize) -> Range<usize> {
+        let offsets = self.edge_offsets();
+        offsets[node] as usize..offsets[node + 1] as usize
+    }
+
+    fn key(&self, i: usize) -> &K {
+        self.item(&self.sections.keys, i)
+    }
+
+    fn value(&self, i: usize) -> &V {
+        self.item(&self.sections.values, i)
+    }
+
+    fn edge(&self, i: usize) -> &E {
+        self.item(&self.sections.edges, i)
+    }
+
+    fn item<T: Pod>(&self, section: &Range<usize>, i: usize) -> &T {
+        let start = section.start + i * mem::size_of::<T>();
+        bytemuck::from_bytes(&self.map[start..start + mem::size_of::<T>()])
+    }
+
+    fn edge_offsets(&self) -> &[u64] {
+        bytemuck::cast_slice(&self.map[self.sections.edge_offsets.clone()])
+    }
+
+    fn edge_ids(&self) -> &[u64] {
+        bytemuck::cast_slice(&self.map[self.sections.edge_ids.clone()])
+    }
+
+    fn targets(&self) -> &[u32] {
+        bytemuck::cast_slice(&self.map[self.sections.targets.clone()])
+    }
+
+    fn sorted_edge_ids(&self) -> &[u64] {
+        bytemuck::cast_slice(&self.map[self.sections.sorted_edge_ids.clone()])
+    }
+
+    fn edge_positions(&self) -> &[u64] {
+        bytemuck::cast_slice(&self.map[self.sections.edge_positions.clone()])
+    }
+}
+
+impl<K, V, E> Debug for MappedGraph<K, V, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedGraph")
+            .field("nodes", &self.node_count)
+            .field("edges", &self.edge_count)
+            .finish_non_exhaustive()
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+impl<K, V, E, S> ReadOnlyGraph<K, V, E, S>
+where
+    K: Pod + Ord + Hash + Sync + Send,
+    V: Pod + Sync + Send,
+    E: Pod + Sync + Send,
+    S: BuildHasher + Clone + Sync + Send,
+{
+    /// Write the graph in the format read by [`MappedGraph`].
+    ///
+    /// `writer` is not buffered, so wrap it in a
+    /// [`BufWriter`](std::io::BufWriter) when writing to a file.
+    ///
+    /// # Errors
+    /// Fails if `writer` fails, if the graph has more than `u32::MAX` nodes,
+    /// or if the graph has dangling edges.
+    pub fn write_mapped<W: Write>(&self, mut writer: W) -> Result<(), SnapshotError> {
+        let mut nodes: Vec<(K, V)> = self.nodes.iter().map(|(k, v)| (*k, *v)).collect();
+        nodes.par_sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        if u32::try_from(nodes.len()).is_err() {
+            return Err(SnapshotError::TooLarge);
+        }
+        let index_of = |key: &K| nodes.binary_search_by(|(k, _)| k.cmp(key)).ok();
+
+        let outgoing = nodes
+            .par_iter()
+            .map(|(key, _)| {
+                let outgoing = self.from.get(key).map_or(&[][..], Vec::as_slice);
+                outgoing
+                    .iter()
+                    .map(|(edge_id, to)| {
+                        let target = index_of(to).ok_or(SnapshotError::Dangling(*edge_id))?;
+                        let edge = self
+                            .get_edge(*edge_id)
+                            .ok_or(SnapshotError::Dangling(*edge_id))?;
+                        Ok((edge_id.0, target as u32, *edge))
+                    })
+                    .collect::<Result<Vec<_>, SnapshotError>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let edge_count: usize = outgoing.iter().map(Vec::len).sum();
+
+        let mut edge_offsets = Vec::with_capacity(nodes.len() + 1);
+        edge_offsets.push(0u64);
+        for edges in &outgoing {
+            edge_offsets.push(edge_offsets.last().unwrap() + edges.len() as u64);
+        }
+        let edges = outgoing.into_iter().flatten();
+        let (edge_ids, (targets, edge_data)): (Vec<u64>, (Vec<u32>, Vec<E>)) =
+            edges.map(|(id, target, edge)| (id, (target, edge))).unzip();
+        let mut sorted: Vec<(u64, u64)> = edge_ids
+            .iter()
+            .enumerate()
+            .map(|(position, id)| (*id, position as u64))
+            .collect();
+        sorted.par_sort_unstable();
+        let (sorted_edge_ids, edge_positions): (Vec<u64>, Vec<u64>) = sorted.into_iter().unzip();
+        let (keys, values): (Vec<K>, Vec<V>) = nodes.into_iter().unzip();
+
+        let mut header = [0u8; ALIGN];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&BYTE_ORDER_MARK.to_ne_bytes());
+        header[16..24].copy_from_slice(&(keys.len() as u64).to_le_bytes());
+        header[24..32].copy_from_slice(&(edge_count as u64).to_le_bytes());
+        for (i, size) in [size_of::<K>(), size_of::<V>(), size_of::<E>()]
+            .into_iter()
+            .enumerate()
+        {
+            header[32 + i * 4..36 + i * 4].copy_from_slice(&size.to_le_bytes());
+        }
+        writer.write_all(&header)?;
+
+        let mut written = ALIGN;
+        let mut write_section = |bytes: &[u8]| -> Result<(), SnapshotError> {
+            let padding = align(written) - written;
+            writer.write_all(&[0; ALIGN][..padding])?;
+            writer.write_all(bytes)?;
+            written += padding + bytes.len();
+            Ok(())
+        };
+        write_section(bytemuck::cast_slice(&keys))?;
+        write_section(bytemuck::cast_slice(&values))?;
+        write_section(bytemuck::cast_slice(&edge_offsets))?;
+        write_section(bytemuck::cast_slice(&edge_ids))?;
+        write_section(bytemuck::cast_slice(&targets))?;
+        write_section(bytemuck::cast_slice(&edge_data))?;
+        write_section(bytemuck::cast_slice(&sorted_edge_ids))?;
+        write_section(bytemuck::cast_slice(&edge_positions))?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// The size of `T` as stored in the header.
+fn size_of<T>() -> u32 {
+    mem::size_of::<T>() as u32
+}
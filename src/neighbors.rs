@@ -0,0 +1,205 @@
+//! Neighbor queries that resolve adjacent nodes' data.
+use std::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+};
+
+use dashmap::mapref::one::Ref;
+
+use crate::{scope, scope::Access, Direction, EdgeId, Graph, ReadOnlyGraph};
+
+impl<'a, K, V, E, S> Graph<K, V, E, S>
+where
+    K: 'a + Eq + Hash + Clone,
+    V: 'a,
+    E: 'a,
+    S: 'a + BuildHasher + Clone,
+{
+    /// Returns the edge id and node of every neighbor of `key` in the given
+    /// `direction`.
+    ///
+    /// The adjacency list is copied up front, so its lock is released
+    /// immediately. Each neighbor is locked only while its [`Ref`] is alive.
+    /// Neighbors that are no longer in the graph are skipped, and a missing
+    /// `key` yields nothing.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::{Direction, Graph};
+    ///
+    /// let graph: Graph<&str, u32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+    /// graph.add_edge("a", "b", ());
+    /// graph.add_edge("a", "c", ());
+    ///
+    /// let total: u32 = graph.neighbors("a", Direction::Outgoing).map(|(_, node)| *node).sum();
+    /// assert_eq!(total, 5);
+    /// assert_eq!(graph.predecessors("c").next().unwrap().1.key(), &"a");
+    /// ```
+    pub fn neighbors<Q>(
+        &'a self,
+        key: &Q,
+        direction: Direction,
+    ) -> impl Iterator<Item = (EdgeId, Ref<'a, K, V>)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.neighbor_ids(key, direction)
+            .into_iter()
+            .filter_map(move |(edge_id, neighbor)| {
+                scope::check::<K, _, _, K>(&self.nodes, &neighbor, Access::Read, "node");
+                Some((edge_id, self.nodes.get::<K>(&neighbor)?))
+            })
+    }
+
+    /// Returns the nodes `key` has edges to. See [`Graph::neighbors`].
+    pub fn successors<Q>(&'a self, key: &Q) -> impl Iterator<Item = (EdgeId, Ref<'a, K, V>)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.neighbors(key, Direction::Outgoing)
+    }
+
+    /// Returns the nodes with edges to `key`. See [`Graph::neighbors`].
+    pub fn predecessors<Q>(&'a self, key: &Q) -> impl Iterator<Item = (EdgeId, Ref<'a, K, V>)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.neighbors(key, Direction::Incoming)
+    }
+
+    /// Returns an owned copy of the edge id, key, and value of every neighbor
+    /// of `key` in the given `direction`.
+    ///
+    /// Unlike [`Graph::neighbors`], no locks are held once this returns.
+    pub fn neighbors_owned<Q>(&self, key: &Q, direction: Direction) -> Vec<(EdgeId, K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.neighbors(key, direction)
+            .map(|(edge_id, node)| (edge_id, node.key().clone(), node.value().clone()))
+            .collect()
+    }
+
+    /// Returns the number of edges originating from `key`.
+    pub fn out_degree<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.degree(key, Direction::Outgoing)
+    }
+
+    /// Returns the number of edges terminating at `key`.
+    pub fn in_degree<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.degree(key, Direction::Incoming)
+    }
+
+    fn degree<Q>(&self, key: &Q, direction: Direction) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.edges_directed(key, direction)
+            .map_or(0, |edges| edges.len())
+    }
+
+    fn neighbor_ids<Q>(&self, key: &Q, direction: Direction) -> Vec<(EdgeId, K)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.edges_directed(key, direction)
+            .map(|edges| edges.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl<'a, K, V, E, S> ReadOnlyGraph<K, V, E, S>
+where
+    K: 'a + Eq + Hash,
+    V: 'a,
+    E: 'a,
+    S: 'a + BuildHasher + Clone,
+{
+    /// Returns the edge id, key, and value of every neighbor of `key` in the
+    /// given `direction`. Neighbors that are not in the graph are skipped,
+    /// and a missing `key` yields nothing.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::{Direction, Graph};
+    ///
+    /// let graph: Graph<&str, u32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+    /// graph.add_edge("a", "c", ());
+    /// graph.add_edge("b", "c", ());
+    /// let graph = graph.into_read_only();
+    ///
+    /// let values: Vec<u32> = graph.predecessors("c").map(|(_, _, value)| *value).collect();
+    /// assert_eq!(values, vec![1, 2]);
+    /// assert_eq!(graph.in_degree("c"), 2);
+    /// ```
+    pub fn neighbors<Q>(
+        &'a self,
+        key: &Q,
+        direction: Direction,
+    ) -> impl Iterator<Item = (EdgeId, &'a K, &'a V)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.edges_directed(key, direction)
+            .into_iter()
+            .flatten()
+            .filter_map(move |(edge_id, neighbor)| {
+                let (key, value) = self.nodes.get_key_value::<K>(neighbor)?;
+                Some((*edge_id, key, value))
+            })
+    }
+
+    /// Returns the nodes `key` has edges to. See [`ReadOnlyGraph::neighbors`].
+    pub fn successors<Q>(&'a self, key: &Q) -> impl Iterator<Item = (EdgeId, &'a K, &'a V)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.neighbors(key, Direction::Outgoing)
+    }
+
+    /// Returns the nodes with edges to `key`. See [`ReadOnlyGraph::neighbors`].
+    pub fn predecessors<Q>(&'a self, key: &Q) -> impl Iterator<Item = (EdgeId, &'a K, &'a V)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.neighbors(key, Direction::Incoming)
+    }
+
+    /// Returns the number of edges originating from `key`.
+    pub fn out_degree<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.edges_directed(key, Direction::Outgoing)
+            .map_or(0, Vec::len)
+    }
+
+    /// Returns the number of edges terminating at `key`.
+    pub fn in_degree<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.edges_directed(key, Direction::Incoming)
+            .map_or(0, Vec::len)
+    }
+}
@@ -1,19 +1,29 @@
 use std::{
     borrow::Borrow,
+    fmt::{self, Debug},
     hash::{BuildHasher, Hash},
     ops,
+    sync::atomic::AtomicU64,
 };
 
 use dashmap::ReadOnlyView;
 
-use crate::{EdgeHasher, EdgeId};
+use crate::{edge_index::EdgeIndex, Dangling, Direction, EdgeHasher, EdgeId, Graph};
 
+/// An immutable view of a [`Graph`], created with
+/// [`Graph::into_read_only`](crate::Graph::into_read_only).
+///
+/// Since no more writes can occur, nodes and edges are accessed through plain references instead
+/// of lock guards.
 pub struct ReadOnlyGraph<K, V, E, S> {
     pub(crate) nodes: ReadOnlyView<K, V, S>,
-    pub(crate) edges: ReadOnlyView<EdgeId, E, EdgeHasher>,
+    pub(crate) edges: ReadOnlyView<EdgeId, (K, E, K), EdgeHasher>,
     pub(crate) to: ReadOnlyView<K, Vec<(EdgeId, K)>, S>,
     pub(crate) from: ReadOnlyView<K, Vec<(EdgeId, K)>, S>,
-    // note: curr_edge_id not needed since no more edges will be added
+    /// Kept for [`ReadOnlyGraph::into_graph`].
+    pub(crate) edge_index: Option<EdgeIndex<K, S>>,
+    /// The id the next edge added after [`ReadOnlyGraph::into_graph`] gets.
+    pub(crate) next_edge_id: u64,
 }
 
 impl<'a, K, V, E, S> ReadOnlyGraph<K, V, E, S>
@@ -48,56 +58,214 @@ where
     }
 
     pub fn get_edge(&self, key: EdgeId) -> Option<&E> {
-        self.edges.get(&key)
+        self.edges.get(&key).map(|(_, edge, _)| edge)
     }
 
+    /// Returns the source and target of an edge, or `None` if the edge is not
+    /// in the graph.
+    pub fn edge_endpoints(&self, edge_id: EdgeId) -> Option<(&K, &K)> {
+        self.edges.get(&edge_id).map(|(from, _, to)| (from, to))
+    }
+
+    /// Returns the id and opposite endpoint of every edge incident to `key` in
+    /// the given `direction`, in the same shape as [`Graph::edges_directed`].
+    ///
+    /// [`Graph::edges_directed`]: crate::Graph::edges_directed
+    pub fn edges_directed<Q>(&'a self, key: &Q, direction: Direction) -> Option<&Vec<(EdgeId, K)>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match direction {
+            Direction::Outgoing => self.from.get(key),
+            Direction::Incoming => self.to.get(key),
+        }
+    }
+
+    /// Shorthand for [`ReadOnlyGraph::edges_directed`] with [`Direction::Outgoing`].
+    pub fn edges_from<Q>(&'a self, key: &Q) -> Option<&Vec<(EdgeId, K)>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.edges_directed(key, Direction::Outgoing)
+    }
+
+    /// Shorthand for [`ReadOnlyGraph::edges_directed`] with [`Direction::Incoming`].
+    pub fn edges_to<Q>(&'a self, key: &Q) -> Option<&Vec<(EdgeId, K)>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.edges_directed(key, Direction::Incoming)
+    }
+
+    #[deprecated(note = "use edges_directed")]
     pub fn edge_ids_from<Q>(&'a self, key: &Q) -> Option<&Vec<(EdgeId, K)>>
     where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        self.from.get(key)
+        self.edges_directed(key, Direction::Outgoing)
     }
 
+    #[deprecated(note = "use edges_directed")]
     pub fn edge_ids_to<Q>(&'a self, key: &Q) -> Option<&Vec<(EdgeId, K)>>
     where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        self.to.get(key)
+        self.edges_directed(key, Direction::Incoming)
     }
 
+    /// Returns the source, data, and target of every edge originating from
+    /// `key`, or `None` if `key` is not in the graph.
+    ///
+    /// Edges that cannot be resolved are skipped. Use
+    /// [`ReadOnlyGraph::try_iter_edges_from`] to find them.
     pub fn iter_edges_from<Q>(&'a self, key: &Q) -> Option<impl Iterator<Item = (&V, &E, &V)> + 'a>
     where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
         let from = self.nodes.get(key)?;
-        let edges_from = self.edge_ids_from(key)?;
-        Some(edges_from.iter().map(move |(edge_id, to)| {
-            (
-                from,
-                self.edges.get(edge_id).unwrap(),
-                self.nodes.get(to.borrow()).unwrap(),
-            )
+        let edges_from = self.edges_from(key)?;
+        Some(edges_from.iter().filter_map(move |(edge_id, to)| {
+            Some((from, self.get_edge(*edge_id)?, self.nodes.get::<K>(to)?))
         }))
     }
 
+    /// Like [`ReadOnlyGraph::iter_edges_from`], but yields an error for edges that cannot be
+    /// resolved instead of skipping them.
+    #[allow(clippy::type_complexity)]
+    pub fn try_iter_edges_from<Q>(
+        &'a self,
+        key: &Q,
+    ) -> Option<impl Iterator<Item = Result<(&V, &E, &V), Dangling<K>>> + 'a>
+    where
+        K: Borrow<Q> + Clone,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (from_key, from) = self.nodes.get_key_value(key)?;
+        let edges_from = self.edges_from(key)?;
+        Some(
+            edges_from
+                .iter()
+                .map(move |(edge_id, to)| self.resolve(from_key, from, *edge_id, to)),
+        )
+    }
+
     pub fn iter_nodes(&self) -> impl Iterator<Item = (&K, &V)> {
         self.nodes.iter()
     }
 
+    /// Returns the source, data, and target of every edge in the graph.
+    ///
+    /// Edges that cannot be resolved are skipped, as they are by `par_iter`.
+    /// Use [`ReadOnlyGraph::try_iter`] to find them.
     pub fn iter(&self) -> impl Iterator<Item = (&V, &E, &V)> {
-        self.nodes
-            .iter()
-            .filter_map(move |(key, value)| self.from.get(key).map(|edges| (value, edges)))
-            .flat_map(move |(from, edges)| {
-                edges.iter().map(move |(edge_id, to_id)| {
-                    let edge = self.edges.get(edge_id).unwrap();
-                    let to = self.nodes.get(to_id).unwrap();
-                    (from, edge, to)
-                })
+        self.from.iter().flat_map(move |(from_key, edges)| {
+            edges.iter().filter_map(move |(edge_id, to)| {
+                Some((
+                    self.nodes.get(from_key)?,
+                    self.get_edge(*edge_id)?,
+                    self.nodes.get(to)?,
+                ))
             })
+        })
+    }
+
+    /// Like [`ReadOnlyGraph::iter`], but yields an error for edges that cannot be resolved
+    /// instead of skipping them.
+    ///
+    /// Graphs created with [`Graph::try_into_read_only`](crate::Graph::try_into_read_only) never
+    /// yield errors.
+    pub fn try_iter(&self) -> impl Iterator<Item = Result<(&V, &E, &V), Dangling<K>>>
+    where
+        K: Clone,
+    {
+        self.from.iter().flat_map(move |(from_key, edges)| {
+            let from = self.nodes.get(from_key);
+            edges.iter().map(move |(edge_id, to)| {
+                let from = from.ok_or_else(|| Dangling::Node {
+                    key: from_key.clone(),
+                    edge_id: *edge_id,
+                })?;
+                self.resolve(from_key, from, *edge_id, to)
+            })
+        })
+    }
+
+    fn resolve<'g>(
+        &'g self,
+        from_key: &K,
+        from: &'g V,
+        edge_id: EdgeId,
+        to_key: &K,
+    ) -> Result<(&'g V, &'g E, &'g V), Dangling<K>>
+    where
+        K: Clone,
+    {
+        let Some(edge) = self.get_edge(edge_id) else {
+            return Err(Dangling::Edge {
+                edge_id,
+                from: from_key.clone(),
+                to: to_key.clone(),
+            });
+        };
+        let Some(to) = self.nodes.get(to_key) else {
+            return Err(Dangling::Node {
+                key: to_key.clone(),
+                edge_id,
+            });
+        };
+        Ok((from, edge, to))
+    }
+}
+
+impl<K, V, E, S> ReadOnlyGraph<K, V, E, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    /// Turn the graph back into a mutable [`Graph`], without copying any nodes
+    /// or edges.
+    ///
+    /// Edge ids continue where the graph left off before it was made
+    /// read-only, so ids of removed edges are never reused, and an
+    /// [edge index](Graph::enable_edge_index) is kept.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<u32, ()> = (0..3).map(|i| (i, ())).collect();
+    /// let first = graph.add_edge(0, 1, ());
+    ///
+    /// let graph = graph.into_read_only().into_graph();
+    /// let second = graph.add_edge(1, 2, ());
+    /// assert_ne!(first, second);
+    /// assert_eq!(graph.edges_from(&0).unwrap().len(), 1);
+    /// ```
+    pub fn into_graph(self) -> Graph<K, V, E, S> {
+        Graph {
+            nodes: self.nodes.into_inner(),
+            edges: self.edges.into_inner(),
+            to: self.to.into_inner(),
+            from: self.from.into_inner(),
+            edge_index: self.edge_index,
+            curr_edge_id: AtomicU64::new(self.next_edge_id),
+        }
+    }
+}
+
+impl<K, V, E, S> From<ReadOnlyGraph<K, V, E, S>> for Graph<K, V, E, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    fn from(graph: ReadOnlyGraph<K, V, E, S>) -> Self {
+        graph.into_graph()
     }
 }
 
@@ -113,3 +281,20 @@ where
         self.get_node(&key).expect("Key not found")
     }
 }
+
+impl<K, V, E, S> Debug for ReadOnlyGraph<K, V, E, S>
+where
+    K: Debug + Eq + Hash,
+    V: Debug,
+    E: Debug,
+    S: BuildHasher + Clone,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadOnlyGraph")
+            .field("nodes", &self.nodes)
+            .field("edges", &self.edges)
+            .field("to", &self.to)
+            .field("from", &self.from)
+            .finish()
+    }
+}
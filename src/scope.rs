@@ -0,0 +1,293 @@
+//! Tracked lock guards and scoped access to a node and its adjacency lists,
+//! plus a debug-mode deadlock detector.
+//!
+//! dashmap deadlocks when a thread locks a shard it already holds, and one of
+//! the locks is a write lock. In debug builds with the `deadlock-detection`
+//! feature, every guard returned by the graph, such as [`Graph::get_node`],
+//! and every lock held by scoped accessors such as
+//! [`Graph::with_node_and_neighbors`], is recorded along with the thread that
+//! took it. Graph methods panic instead of hanging when they would re-enter
+//! one of them. Guards moved to another thread still count against the thread
+//! that created them.
+use std::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    ops::{Deref, DerefMut},
+};
+
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    thread::{self, ThreadId},
+};
+
+use dashmap::{
+    mapref::one::{MappedRef, MappedRefMut, Ref, RefMut},
+    DashMap,
+};
+
+use crate::{EdgeId, Graph};
+
+/// How a shard is locked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+}
+
+/// A shard lock recorded by the deadlock detector.
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+#[derive(Clone, Copy)]
+struct Lock {
+    token: u64,
+    thread: ThreadId,
+    map: usize,
+    shard: usize,
+    access: Access,
+}
+
+/// Shard locks held by tracked guards and scoped accessors, on any thread.
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+static HELD: Mutex<Vec<Lock>> = Mutex::new(Vec::new());
+
+/// Distinguishes otherwise identical [`Lock`]s, so each is released once.
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+fn held() -> MutexGuard<'static, Vec<Lock>> {
+    // Detected deadlocks panic after the registry is unlocked, but other
+    // panics should not disable the detector either.
+    HELD.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Panics if this thread already holds a lock on `shard` of the map at `map`
+/// that conflicts with `access`.
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+fn assert_not_held(map: usize, shard: usize, access: Access, what: &str) {
+    let thread = thread::current().id();
+    let conflict = held().iter().any(|lock| {
+        lock.thread == thread
+            && lock.map == map
+            && lock.shard == shard
+            && (access == Access::Write || lock.access == Access::Write)
+    });
+    assert!(
+        !conflict,
+        "deadlock detected: this thread already holds a lock on the {what} shard \
+         containing this key. Drop guards returned by the graph, and references from \
+         scoped accessors like `with_node_and_neighbors`, before writing to the same \
+         part of the graph."
+    );
+}
+
+/// Panics if locking the shard of `map` containing `key` would deadlock
+/// because this thread already holds it. Does nothing in release builds.
+#[inline]
+pub(crate) fn check<K, V, S, Q>(map: &DashMap<K, V, S>, key: &Q, access: Access, what: &str)
+where
+    K: Eq + Hash + Borrow<Q>,
+    S: BuildHasher + Clone,
+    Q: Hash + Eq + ?Sized,
+{
+    #[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+    assert_not_held(
+        map as *const _ as usize,
+        map.determine_map(key),
+        access,
+        what,
+    );
+    #[cfg(not(all(debug_assertions, feature = "deadlock-detection")))]
+    let _ = (map, key, access, what);
+}
+
+/// Records that a shard lock is held until it is dropped.
+pub(crate) struct Held {
+    #[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+    token: u64,
+}
+
+/// Check and record a lock on the shard of `map` containing `key`. The lock
+/// itself must be taken after this is called.
+pub(crate) fn hold<K, V, S, Q>(map: &DashMap<K, V, S>, key: &Q, access: Access, what: &str) -> Held
+where
+    K: Eq + Hash + Borrow<Q>,
+    S: BuildHasher + Clone,
+    Q: Hash + Eq + ?Sized,
+{
+    check(map, key, access, what);
+    #[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+    {
+        let token = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+        held().push(Lock {
+            token,
+            thread: thread::current().id(),
+            map: map as *const _ as usize,
+            shard: map.determine_map(key),
+            access,
+        });
+        Held { token }
+    }
+    #[cfg(not(all(debug_assertions, feature = "deadlock-detection")))]
+    Held {}
+}
+
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+impl Drop for Held {
+    fn drop(&mut self) {
+        let mut held = held();
+        if let Some(i) = held.iter().position(|lock| lock.token == self.token) {
+            held.swap_remove(i);
+        }
+    }
+}
+
+/// A lock guard returned by a [`Graph`], such as a [`Ref`] from
+/// [`Graph::get_node`].
+///
+/// It dereferences to the guarded value. In debug builds, it is registered
+/// with the deadlock detector until it is dropped, so writing to the same
+/// shard on this thread in the meantime panics instead of deadlocking.
+pub struct Tracked<G> {
+    guard: G,
+    _held: Held,
+}
+
+impl<G> Tracked<G> {
+    pub(crate) fn new(guard: G, held: Held) -> Self {
+        Self { guard, _held: held }
+    }
+}
+
+impl<G: Deref> Deref for Tracked<G> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+
+impl<G: DerefMut> DerefMut for Tracked<G> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+
+impl<G: Debug> Debug for Tracked<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.guard.fmt(f)
+    }
+}
+
+impl<'a, K: Eq + Hash, V> Tracked<Ref<'a, K, V>> {
+    pub fn key(&self) -> &K {
+        self.guard.key()
+    }
+
+    pub fn value(&self) -> &V {
+        self.guard.value()
+    }
+
+    pub fn pair(&self) -> (&K, &V) {
+        self.guard.pair()
+    }
+}
+
+impl<'a, K: Eq + Hash, V> Tracked<RefMut<'a, K, V>> {
+    pub fn key(&self) -> &K {
+        self.guard.key()
+    }
+
+    pub fn value(&self) -> &V {
+        self.guard.value()
+    }
+
+    pub fn value_mut(&mut self) -> &mut V {
+        self.guard.value_mut()
+    }
+
+    pub fn pair(&self) -> (&K, &V) {
+        self.guard.pair()
+    }
+
+    pub fn pair_mut(&mut self) -> (&K, &mut V) {
+        self.guard.pair_mut()
+    }
+}
+
+impl<'a, K: Eq + Hash, V, T> Tracked<MappedRef<'a, K, V, T>> {
+    pub fn key(&self) -> &K {
+        self.guard.key()
+    }
+
+    pub fn value(&self) -> &T {
+        self.guard.value()
+    }
+}
+
+impl<'a, K: Eq + Hash, V, T> Tracked<MappedRefMut<'a, K, V, T>> {
+    pub fn key(&self) -> &K {
+        self.guard.key()
+    }
+
+    pub fn value(&self) -> &T {
+        self.guard.value()
+    }
+
+    pub fn value_mut(&mut self) -> &mut T {
+        self.guard.value_mut()
+    }
+}
+
+impl<K, V, E, S> Graph<K, V, E, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    /// Call `f` with a node's value and its outgoing and incoming edges,
+    /// returning its result. Returns `None` if the node is not in the graph.
+    ///
+    /// This is a safe alternative to holding the guards from
+    /// [`Graph::get_node`], [`Graph::edges_from`], and [`Graph::edges_to`] at
+    /// once. `f` may read any part of the graph, but must not write to this
+    /// node or its adjacency lists. In debug builds, doing so panics instead
+    /// of deadlocking.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::Graph;
+    ///
+    /// let graph: Graph<&str, u32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+    /// graph.add_edge("a", "b", ());
+    /// graph.add_edge("c", "a", ());
+    ///
+    /// let sum = graph.with_node_and_neighbors("a", |value, outgoing, incoming| {
+    ///     let neighbors = outgoing.iter().chain(incoming);
+    ///     value + neighbors.map(|(_, key)| *graph.get_node(key).unwrap()).sum::<u32>()
+    /// });
+    /// assert_eq!(sum, Some(6));
+    /// ```
+    pub fn with_node_and_neighbors<Q, R, F>(&self, key: &Q, f: F) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce(&V, &[(EdgeId, K)], &[(EdgeId, K)]) -> R,
+    {
+        let _node_held = hold(&self.nodes, key, Access::Read, "node");
+        let node = self.nodes.get(key)?;
+        let _outgoing_held = hold(&self.from, key, Access::Read, "outgoing edge");
+        let outgoing = self.from.get(key);
+        let _incoming_held = hold(&self.to, key, Access::Read, "incoming edge");
+        let incoming = self.to.get(key);
+
+        Some(f(
+            node.value(),
+            outgoing.as_ref().map_or(&[], |edges| edges.as_slice()),
+            incoming.as_ref().map_or(&[], |edges| edges.as_slice()),
+        ))
+    }
+}
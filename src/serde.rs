@@ -0,0 +1,206 @@
+//! [`serde`] support for graphs, enabled by the `serde` feature.
+//!
+//! Graphs are represented as a struct with a list of `(key, value)` nodes, a
+//! list of `(edge_id, from, to, edge)` edges, and the next unused [`EdgeId`].
+//! [`ReadOnlyGraph`] uses the same representation, so the two are
+//! interchangeable.
+use std::{
+    collections::HashSet,
+    hash::{BuildHasher, Hash},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use ::serde::{
+    de::Error as _,
+    ser::{SerializeSeq, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{EdgeHasher, EdgeId, Graph, ReadOnlyGraph};
+
+impl Serialize for EdgeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct("EdgeId", &self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for EdgeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "EdgeId")]
+        struct Repr(u64);
+
+        Repr::deserialize(deserializer).map(|Repr(id)| EdgeId(id))
+    }
+}
+
+impl<K, V, E, S> Serialize for Graph<K, V, E, S>
+where
+    K: Serialize + Eq + Hash,
+    V: Serialize,
+    E: Serialize,
+    S: BuildHasher + Clone,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let next_edge_id = self.curr_edge_id.load(Ordering::Relaxed);
+
+        let mut graph = serializer.serialize_struct("Graph", 3)?;
+        graph.serialize_field("nodes", &Nodes(self))?;
+        graph.serialize_field("edges", &Edges(self))?;
+        graph.serialize_field("next_edge_id", &next_edge_id)?;
+        graph.end()
+    }
+}
+
+impl<K, V, E, S> Serialize for ReadOnlyGraph<K, V, E, S>
+where
+    K: Serialize + Eq + Hash,
+    V: Serialize,
+    E: Serialize,
+    S: BuildHasher + Clone,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let mut graph = serializer.serialize_struct("Graph", 3)?;
+        graph.serialize_field("nodes", &Nodes(self))?;
+        graph.serialize_field("edges", &Edges(self))?;
+        graph.serialize_field("next_edge_id", &self.next_edge_id)?;
+        graph.end()
+    }
+}
+
+/// Serializes a graph's nodes as a sequence of `(key, value)` pairs.
+struct Nodes<'g, G>(&'g G);
+
+/// Serializes a graph's edges as a sequence of `(edge_id, from, to, edge)` tuples.
+struct Edges<'g, G>(&'g G);
+
+impl<K, V, E, S> Serialize for Nodes<'_, Graph<K, V, E, S>>
+where
+    K: Serialize + Eq + Hash,
+    V: Serialize,
+    S: BuildHasher + Clone,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.nodes.len()))?;
+        for node in self.0.nodes.iter() {
+            seq.serialize_element(&(node.key(), node.value()))?;
+        }
+        seq.end()
+    }
+}
+
+impl<K, V, E, S> Serialize for Edges<'_, Graph<K, V, E, S>>
+where
+    K: Serialize + Eq + Hash,
+    E: Serialize,
+    S: BuildHasher + Clone,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let graph = self.0;
+        let len = graph.from.iter().map(|outgoing| outgoing.len()).sum();
+        let mut seq = serializer.serialize_seq(Some(len))?;
+        for outgoing in graph.from.iter() {
+            for (edge_id, to) in outgoing.value() {
+                let edge = graph.edges.get(edge_id).ok_or_else(|| dangling(*edge_id))?;
+                seq.serialize_element(&(edge_id, outgoing.key(), to, &edge.1))?;
+            }
+        }
+        seq.end()
+    }
+}
+
+impl<K, V, E, S> Serialize for Nodes<'_, ReadOnlyGraph<K, V, E, S>>
+where
+    K: Serialize + Eq + Hash,
+    V: Serialize,
+    S: BuildHasher + Clone,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.0.nodes.iter())
+    }
+}
+
+impl<K, V, E, S> Serialize for Edges<'_, ReadOnlyGraph<K, V, E, S>>
+where
+    K: Serialize + Eq + Hash,
+    E: Serialize,
+    S: BuildHasher + Clone,
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let graph = self.0;
+        let len = graph.from.values().map(Vec::len).sum();
+        let mut seq = serializer.serialize_seq(Some(len))?;
+        for (from, outgoing) in graph.from.iter() {
+            for (edge_id, to) in outgoing {
+                let edge = graph.get_edge(*edge_id).ok_or_else(|| dangling(*edge_id))?;
+                seq.serialize_element(&(edge_id, from, to, edge))?;
+            }
+        }
+        seq.end()
+    }
+}
+
+fn dangling<Err: ::serde::ser::Error>(edge_id: EdgeId) -> Err {
+    Err::custom(format!("edge {} has no edge data", edge_id.0))
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Graph")]
+struct GraphRepr<K, V, E> {
+    nodes: Vec<(K, V)>,
+    edges: Vec<(EdgeId, K, K, E)>,
+    next_edge_id: u64,
+}
+
+impl<'de, K, V, E, S> Deserialize<'de> for Graph<K, V, E, S>
+where
+    K: Deserialize<'de> + Eq + Hash + Clone,
+    V: Deserialize<'de>,
+    E: Deserialize<'de>,
+    S: Default + BuildHasher + Clone,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let GraphRepr {
+            nodes,
+            edges,
+            next_edge_id,
+        } = GraphRepr::<K, V, E>::deserialize(deserializer)?;
+
+        let graph: Self = nodes.into_iter().collect();
+        let mut edge_ids: HashSet<EdgeId, EdgeHasher> = HashSet::default();
+        let mut max_edge_id = None;
+        for (edge_id, from, to, edge) in edges {
+            if !edge_ids.insert(edge_id) {
+                return Err(D::Error::custom(format!("duplicate edge id {}", edge_id.0)));
+            }
+            if !graph.nodes.contains_key(&from) || !graph.nodes.contains_key(&to) {
+                return Err(D::Error::custom(format!(
+                    "edge {} refers to a missing node",
+                    edge_id.0
+                )));
+            }
+            max_edge_id = max_edge_id.max(Some(edge_id.0));
+            graph.insert_edge(edge_id, from, to, edge);
+        }
+
+        // Never hand out an id that is already taken, even if `next_edge_id`
+        // was tampered with.
+        let next_edge_id = next_edge_id.max(max_edge_id.map_or(0, |id| id + 1));
+        Ok(Self {
+            curr_edge_id: AtomicU64::new(next_edge_id),
+            ..graph
+        })
+    }
+}
+
+impl<'de, K, V, E, S> Deserialize<'de> for ReadOnlyGraph<K, V, E, S>
+where
+    K: Deserialize<'de> + Eq + Hash + Clone,
+    V: Deserialize<'de>,
+    E: Deserialize<'de>,
+    S: Default + BuildHasher + Clone,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Graph::deserialize(deserializer).map(Graph::into_read_only)
+    }
+}
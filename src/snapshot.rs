@@ -0,0 +1,597 @@
+//! A compact, versioned binary snapshot format for [`ReadOnlyGraph`]s, enabled
+//! by the `snapshot` feature.
+//!
+//! Snapshots are much faster to load than rebuilding a graph from its source
+//! data, and both writing and loading them is parallelized with rayon. Node
+//! keys, node values, and edge data are stored with the [`Encode`] and
+//! [`Decode`] traits, which are implemented for common standard library types.
+//!
+//! ```
+//! use std::hash::RandomState;
+//! use carpet::{Graph, ReadOnlyGraph};
+//!
+//! let graph: Graph<u32, String, f64> = (0..3).map(|i| (i, i.to_string())).collect();
+//! graph.add_edge(0, 1, 0.5);
+//! graph.add_edge(1, 2, 1.5);
+//! let graph = graph.into_read_only();
+//!
+//! let mut snapshot = Vec::new();
+//! graph.write_snapshot(&mut snapshot).unwrap();
+//!
+//! let loaded: ReadOnlyGraph<u32, String, f64, RandomState> = ReadOnlyGraph::read_snapshot(&snapshot[..]).unwrap();
+//! assert_eq!(loaded.get_node(&1).unwrap(), "1");
+//! assert_eq!(loaded.iter().count(), 2);
+//! ```
+//!
+//! # Format
+//!
+//! All integers are little-endian. A snapshot consists of:
+//!
+//! 1. A header: the [`MAGIC`] bytes, the format [`VERSION`] as a `u32`, four
+//!    reserved bytes, the number of nodes `n` as a `u64`, and the number of
+//!    edges `m` as a `u64`.
+//! 2. The node table: `n + 1` `u64` byte offsets into the node data, followed
+//!    by the node data. Each node is an encoded key followed by an encoded value.
+//! 3. Outgoing edges in compressed sparse row form: `n + 1` `u64` offsets into
+//!    the edge arrays, `m` `u64` [`EdgeId`]s, and `m` `u32` target node
+//!    indices. The outgoing edges of node `i` are at `offsets[i]..offsets[i + 1]`.
+//! 4. The edge data: `m + 1` `u64` byte offsets into the edge data, followed by
+//!    the edge data itself, in the same order as the edge arrays.
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+    io::{Read, Write},
+    mem,
+    ops::Range,
+};
+
+use dashmap::DashMap;
+use rayon::prelude::*;
+
+use crate::{EdgeHasher, EdgeId, ReadOnlyGraph, SnapshotError};
+
+/// The first bytes of every snapshot.
+pub const MAGIC: [u8; 8] = *b"CARPETSN";
+
+/// The current version of the snapshot format.
+pub const VERSION: u32 = 1;
+
+const HEADER_LEN: usize = MAGIC.len() + 4 + 4 + 8 + 8;
+
+/// Nodes are encoded in chunks of this size, one chunk per rayon task.
+const CHUNK_SIZE: usize = 4096;
+
+/// Types that can be written to a snapshot.
+pub trait Encode {
+    /// Append the encoded value to `buf`.
+    fn encode(&self, buf: &mut Vec<u8>);
+}
+
+/// Types that can be read from a snapshot.
+pub trait Decode: Sized {
+    /// Decode a value from the start of `input`, advancing it past the
+    /// consumed bytes.
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError>;
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], SnapshotError> {
+    if input.len() < len {
+        return Err(SnapshotError::UnexpectedEof);
+    }
+    let (taken, rest) = input.split_at(len);
+    *input = rest;
+    Ok(taken)
+}
+
+macro_rules! impl_codec_for_numbers {
+    ($($ty:ty),*) => {
+        $(
+            impl Encode for $ty {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+
+            impl Decode for $ty {
+                fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+                    let bytes = take(input, mem::size_of::<$ty>())?;
+                    Ok(<$ty>::from_le_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_for_numbers!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl Encode for usize {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (*self as u64).encode(buf);
+    }
+}
+
+impl Decode for usize {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        usize::try_from(u64::decode(input)?).map_err(|_| SnapshotError::Corrupt("usize overflow"))
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::Corrupt("invalid bool")),
+        }
+    }
+}
+
+impl Encode for () {
+    fn encode(&self, _buf: &mut Vec<u8>) {}
+}
+
+impl Decode for () {
+    fn decode(_input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        Ok(())
+    }
+}
+
+impl Encode for EdgeId {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+    }
+}
+
+impl Decode for EdgeId {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        u64::decode(input).map(EdgeId)
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.len().encode(buf);
+        buf.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.as_str().encode(buf);
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        let len = usize::decode(input)?;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| SnapshotError::Corrupt("invalid UTF-8"))
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.len().encode(buf);
+        for item in self {
+            item.encode(buf);
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.as_slice().encode(buf);
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        let len = usize::decode(input)?;
+        // Don't trust `len` for preallocation, it may be corrupt
+        let mut items = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            items.push(T::decode(input)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            None => buf.push(0),
+            Some(value) => {
+                buf.push(1);
+                value.encode(buf);
+            }
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        match u8::decode(input)? {
+            0 => Ok(None),
+            1 => T::decode(input).map(Some),
+            _ => Err(SnapshotError::Corrupt("invalid option tag")),
+        }
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+        self.1.encode(buf);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(input: &mut &[u8]) -> Result<Self, SnapshotError> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (**self).encode(buf);
+    }
+}
+
+/// Encoded nodes and outgoing edges for a contiguous range of nodes.
+#[derive(Default)]
+struct Chunk {
+    node_lens: Vec<u64>,
+    node_data: Vec<u8>,
+    degrees: Vec<u64>,
+    edge_ids: Vec<u64>,
+    targets: Vec<u32>,
+    edge_lens: Vec<u64>,
+    edge_data: Vec<u8>,
+}
+
+impl<K, V, E, S> ReadOnlyGraph<K, V, E, S>
+where
+    K: Eq + Hash + Sync + Send + Encode,
+    V: Sync + Send + Encode,
+    E: Sync + Send + Encode,
+    S: BuildHasher + Clone + Sync + Send,
+{
+    /// Write a [snapshot](crate::snapshot) of the graph to `writer`.
+    ///
+    /// Nodes and edges are encoded in parallel. `writer` is not buffered, so
+    /// wrap it in a [`BufWriter`](std::io::BufWriter) when writing to a file.
+    ///
+    /// # Errors
+    /// Fails if `writer` fails, if the graph has more than `u32::MAX` nodes,
+    /// or if the graph has dangling edges.
+    pub fn write_snapshot<W: Write>(&self, mut writer: W) -> Result<(), SnapshotError> {
+        let nodes: Vec<(&K, &V)> = self.nodes.iter().collect();
+        let node_count = u32::try_from(nodes.len()).map_err(|_| SnapshotError::TooLarge)?;
+        let index: HashMap<&K, u32> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, (key, _))| (*key, i as u32))
+            .collect();
+
+        let chunks = nodes
+            .par_chunks(CHUNK_SIZE)
+            .map(|nodes| self.encode_chunk(nodes, &index))
+            .collect::<Result<Vec<_>, _>>()?;
+        let edge_count: usize = chunks.iter().map(|chunk| chunk.edge_ids.len()).sum();
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(&MAGIC);
+        VERSION.encode(&mut header);
+        0u32.encode(&mut header);
+        (node_count as u64).encode(&mut header);
+        (edge_count as u64).encode(&mut header);
+        writer.write_all(&header)?;
+
+        write_offsets(&mut writer, chunks.iter().flat_map(|c| &c.node_lens))?;
+        for chunk in &chunks {
+            writer.write_all(&chunk.node_data)?;
+        }
+        write_offsets(&mut writer, chunks.iter().flat_map(|c| &c.degrees))?;
+        for chunk in &chunks {
+            write_u64s(&mut writer, &chunk.edge_ids)?;
+        }
+        for chunk in &chunks {
+            let mut buf = Vec::with_capacity(chunk.targets.len() * 4);
+            for target in &chunk.targets {
+                target.encode(&mut buf);
+            }
+            writer.write_all(&buf)?;
+        }
+        write_offsets(&mut writer, chunks.iter().flat_map(|c| &c.edge_lens))?;
+        for chunk in &chunks {
+            writer.write_all(&chunk.edge_data)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn encode_chunk(
+        &self,
+        nodes: &[(&K, &V)],
+        index: &HashMap<&K, u32>,
+    ) -> Result<Chunk, SnapshotError> {
+        let mut chunk = Chunk::default();
+        for (key, value) in nodes {
+            let start = chunk.node_data.len();
+            key.encode(&mut chunk.node_data);
+            value.encode(&mut chunk.node_data);
+            chunk.node_lens.push((chunk.node_data.len() - start) as u64);
+
+            let outgoing = self.from.get(*key).map_or(&[][..], Vec::as_slice);
+            chunk.degrees.push(outgoing.len() as u64);
+            for (edge_id, to) in outgoing {
+                let target = index.get(to).ok_or(SnapshotError::Dangling(*edge_id))?;
+                let edge = self
+                    .get_edge(*edge_id)
+                    .ok_or(SnapshotError::Dangling(*edge_id))?;
+                chunk.edge_ids.push(edge_id.0);
+                chunk.targets.push(*target);
+
+                let start = chunk.edge_data.len();
+                edge.encode(&mut chunk.edge_data);
+                chunk.edge_lens.push((chunk.edge_data.len() - start) as u64);
+            }
+        }
+        Ok(chunk)
+    }
+}
+
+/// Write the running totals of `lens`, starting with 0.
+fn write_offsets<'a, W: Write>(
+    writer: &mut W,
+    lens: impl Iterator<Item = &'a u64>,
+) -> Result<(), SnapshotError> {
+    let mut buf = Vec::new();
+    let mut offset = 0u64;
+    offset.encode(&mut buf);
+    for len in lens {
+        offset += len;
+        offset.encode(&mut buf);
+    }
+    writer.write_all(&buf)?;
+    Ok(())
+}
+
+fn write_u64s<W: Write>(writer: &mut W, values: &[u64]) -> Result<(), SnapshotError> {
+    let mut buf = Vec::with_capacity(values.len() * 8);
+    for value in values {
+        value.encode(&mut buf);
+    }
+    writer.write_all(&buf)?;
+    Ok(())
+}
+
+/// A snapshot's sections, borrowed from the snapshot's bytes.
+struct Sections<'a> {
+    node_offsets: &'a [u8],
+    node_data: &'a [u8],
+    edge_offsets: &'a [u8],
+    edge_ids: &'a [u8],
+    targets: &'a [u8],
+    edge_data_offsets: &'a [u8],
+    edge_data: &'a [u8],
+}
+
+impl<'a> Sections<'a> {
+    fn parse(mut input: &'a [u8]) -> Result<(Self, usize, usize), SnapshotError> {
+        let input = &mut input;
+        if take(input, MAGIC.len()).map_err(|_| SnapshotError::BadMagic)? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = u32::decode(input)?;
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let _reserved = u32::decode(input)?;
+        let node_count = usize::decode(input)?;
+        let edge_count = usize::decode(input)?;
+        if node_count > u32::MAX as usize {
+            return Err(SnapshotError::Corrupt("too many nodes"));
+        }
+
+        let array = |input: &mut &'a [u8], len: usize, width: usize| {
+            let bytes = len
+                .checked_mul(width)
+                .ok_or(SnapshotError::Corrupt("array length overflow"))?;
+            take(input, bytes)
+        };
+        let node_offsets = array(input, node_count + 1, 8)?;
+        let node_data_len = read_u64(node_offsets, node_count) as usize;
+        let node_data = take(input, node_data_len)?;
+        let edge_offsets = array(input, node_count + 1, 8)?;
+        let edge_ids = array(input, edge_count, 8)?;
+        let targets = array(input, edge_count, 4)?;
+        let edge_data_offsets = array(input, edge_count + 1, 8)?;
+        let edge_data_len = read_u64(edge_data_offsets, edge_count) as usize;
+        let edge_data = take(input, edge_data_len)?;
+        if !input.is_empty() {
+            return Err(SnapshotError::Corrupt("trailing bytes"));
+        }
+
+        // Each node's edges must directly follow the previous node's, so that
+        // every edge belongs to exactly one source node.
+        let mut previous = 0;
+        for i in 0..=node_count {
+            let offset = read_u64(edge_offsets, i);
+            if (i == 0 && offset != 0) || offset < previous {
+                return Err(SnapshotError::Corrupt("edge offsets out of order"));
+            }
+            previous = offset;
+        }
+        if previous != edge_count as u64 {
+            return Err(SnapshotError::Corrupt(
+                "edge offsets do not cover every edge",
+            ));
+        }
+
+        let sections = Self {
+            node_offsets,
+            node_data,
+            edge_offsets,
+            edge_ids,
+            targets,
+            edge_data_offsets,
+            edge_data,
+        };
+        Ok((sections, node_count, edge_count))
+    }
+
+    /// The range of `data` between the `i`th and `i + 1`th offset.
+    fn slice(offsets: &[u8], data: &'a [u8], i: usize) -> Result<&'a [u8], SnapshotError> {
+        let start = read_u64(offsets, i) as usize;
+        let end = read_u64(offsets, i + 1) as usize;
+        data.get(start..end)
+            .ok_or(SnapshotError::Corrupt("offset out of bounds"))
+    }
+
+    /// The range of edges originating from `node`. Edge offsets are
+    /// validated when parsing, so this is always in bounds.
+    fn edge_range(&self, node: usize) -> Range<usize> {
+        let start = read_u64(self.edge_offsets, node) as usize;
+        let end = read_u64(self.edge_offsets, node + 1) as usize;
+        start..end
+    }
+}
+
+fn read_u64(bytes: &[u8], i: usize) -> u64 {
+    u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap())
+}
+
+/// Decode a value that must take up all of `bytes`.
+fn decode_exact<T: Decode>(mut bytes: &[u8]) -> Result<T, SnapshotError> {
+    let value = T::decode(&mut bytes)?;
+    if bytes.is_empty() {
+        Ok(value)
+    } else {
+        Err(SnapshotError::Corrupt("trailing bytes in record"))
+    }
+}
+
+impl<K, V, E, S> ReadOnlyGraph<K, V, E, S>
+where
+    K: Eq + Hash + Clone + Sync + Send + Decode,
+    V: Sync + Send + Decode,
+    E: Sync + Send + Decode,
+    S: Default + BuildHasher + Clone + Sync + Send,
+{
+    /// Load a graph from a [snapshot](crate::snapshot) read from `reader`.
+    ///
+    /// Snapshots do not store the next edge id, so edges added after
+    /// [`ReadOnlyGraph::into_graph`] get ids greater than every edge in the
+    /// snapshot, and ids of edges removed before it was written may be reused.
+    ///
+    /// # Errors
+    /// Fails if `reader` fails, or if the snapshot is invalid.
+    pub fn read_snapshot<R: Read>(mut reader: R) -> Result<Self, SnapshotError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::from_snapshot_bytes(&bytes)
+    }
+
+    /// Load a graph from a [snapshot](crate::snapshot) that is already in memory.
+    ///
+    /// # Errors
+    /// Fails if the snapshot is invalid.
+    pub fn from_snapshot_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let (sections, node_count, edge_count) = Sections::parse(bytes)?;
+
+        let nodes = (0..node_count)
+            .into_par_iter()
+            .map(|i| {
+                let bytes = Sections::slice(sections.node_offsets, sections.node_data, i)?;
+                decode_exact::<(K, V)>(bytes)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let edge_id = |e: usize| EdgeId(read_u64(sections.edge_ids, e));
+        let target = |e: usize| -> Result<usize, SnapshotError> {
+            let target = read_u32(sections.targets, e) as usize;
+            if target < node_count {
+                Ok(target)
+            } else {
+                Err(SnapshotError::Corrupt("edge target out of bounds"))
+            }
+        };
+
+        let from: DashMap<K, Vec<(EdgeId, K)>, S> = (0..node_count)
+            .into_par_iter()
+            .map(|i| {
+                let outgoing = sections
+                    .edge_range(i)
+                    .map(|e| Ok((edge_id(e), nodes[target(e)?].0.clone())))
+                    .collect::<Result<Vec<_>, SnapshotError>>()?;
+                Ok((nodes[i].0.clone(), outgoing))
+            })
+            .filter(|entry| !matches!(entry, Ok((_, outgoing)) if outgoing.is_empty()))
+            .collect::<Result<_, SnapshotError>>()?;
+
+        // Invert the outgoing edges to find each node's incoming edges
+        let mut incoming: Vec<Vec<(EdgeId, usize)>> = vec![Vec::new(); node_count];
+        for i in 0..node_count {
+            for e in sections.edge_range(i) {
+                incoming[target(e)?].push((edge_id(e), i));
+            }
+        }
+        let to: DashMap<K, Vec<(EdgeId, K)>, S> = incoming
+            .into_par_iter()
+            .enumerate()
+            .filter(|(_, incoming)| !incoming.is_empty())
+            .map(|(i, mut incoming)| {
+                incoming.sort_unstable_by_key(|(edge_id, _)| edge_id.0);
+                let incoming = incoming
+                    .into_iter()
+                    .map(|(edge_id, from)| (edge_id, nodes[from].0.clone()))
+                    .collect();
+                (nodes[i].0.clone(), incoming)
+            })
+            .collect();
+
+        let edges: DashMap<EdgeId, (K, E, K), EdgeHasher> = (0..node_count)
+            .into_par_iter()
+            .flat_map_iter(|i| {
+                let (nodes, sections) = (&nodes, &sections);
+                sections.edge_range(i).map(move |e| {
+                    let bytes = Sections::slice(sections.edge_data_offsets, sections.edge_data, e)?;
+                    let edge = decode_exact::<E>(bytes)?;
+                    Ok((
+                        edge_id(e),
+                        (nodes[i].0.clone(), edge, nodes[target(e)?].0.clone()),
+                    ))
+                })
+            })
+            .collect::<Result<_, SnapshotError>>()?;
+        if edges.len() != edge_count {
+            return Err(SnapshotError::Corrupt("duplicate edge id"));
+        }
+
+        let next_edge_id = edges.iter().map(|edge| edge.key().0 + 1).max().unwrap_or(0);
+        let nodes: DashMap<K, V, S> = nodes.into_par_iter().collect();
+        if nodes.len() != node_count {
+            return Err(SnapshotError::Corrupt("duplicate node key"));
+        }
+
+        Ok(ReadOnlyGraph {
+            nodes: nodes.into_read_only(),
+            edges: edges.into_read_only(),
+            to: to.into_read_only(),
+            from: from.into_read_only(),
+            edge_index: None,
+            next_edge_id,
+        })
+    }
+}
@@ -1,4 +1,7 @@
-use crate::Graph;
+use crate::{
+    visit::{Bfs, Dfs, DfsPostOrder},
+    Dangling, Direction, EdgeId, Graph, GraphError,
+};
 
 type UserId = u64;
 
@@ -84,3 +87,1031 @@ fn test_from_iter() {
     // This is the same edge
     assert_eq!(bob_from_alice.0, alice_to_bob.0);
 }
+
+#[test]
+fn test_remove_node() {
+    let graph: Graph<u64, &str> = [(1, "a"), (2, "b"), (3, "c")].into_iter().collect();
+    graph.add_edge(1, 2, ());
+    graph.add_edge(2, 3, ());
+    graph.add_edge(3, 1, ());
+    graph.add_edge(2, 2, ());
+
+    assert_eq!(graph.remove_node(&2), Some("b"));
+    assert_eq!(graph.remove_node(&2), None);
+    assert_eq!(graph.len(), 2);
+
+    // Only the edge from 3 to 1 survives
+    assert_eq!(graph.edges.len(), 1);
+    assert!(graph.edges_from(&1).unwrap().is_empty());
+    assert!(graph.edges_to(&3).unwrap().is_empty());
+    assert!(graph.edges_from(&2).is_none());
+    assert!(graph.edges_to(&2).is_none());
+
+    let from_3 = graph.edges_from(&3).unwrap();
+    assert_eq!(from_3.len(), 1);
+    assert_eq!(from_3[0].1, 1);
+}
+
+#[test]
+fn test_remove_edge() {
+    let graph: UserGraph = [
+        User {
+            id: 1,
+            name: "Alice".to_string(),
+        },
+        User {
+            id: 2,
+            name: "Bob".to_string(),
+        },
+    ]
+    .into_iter()
+    .collect();
+    graph.add_edge(1, 2, Relationship::Follows);
+    graph.add_edge(1, 2, Relationship::Blocks);
+    graph.add_edge(2, 1, Relationship::Follows);
+
+    let blocks = graph.edges_from(&1).unwrap()[1].0;
+    assert_eq!(
+        graph.remove_edge(blocks),
+        Some((1, Relationship::Blocks, 2))
+    );
+    assert_eq!(graph.remove_edge(blocks), None);
+    assert!(graph.get_edge(blocks).is_none());
+
+    let from_alice = graph.edges_from(&1).unwrap();
+    assert_eq!(from_alice.len(), 1);
+    assert_eq!(
+        *graph.get_edge(from_alice[0].0).unwrap(),
+        Relationship::Follows
+    );
+
+    let to_bob = graph.edges_to(&2).unwrap();
+    assert_eq!(to_bob.len(), 1);
+    assert_eq!(to_bob[0].0, from_alice[0].0);
+}
+
+#[test]
+fn test_try_add_edge() {
+    let graph: Graph<u64, &str> = [(1, "a"), (2, "b")].into_iter().collect();
+
+    assert_eq!(
+        graph.try_add_edge(3, 1, ()),
+        Err(GraphError::MissingSource(3))
+    );
+    assert_eq!(
+        graph.try_add_edge(1, 3, ()),
+        Err(GraphError::MissingTarget(3))
+    );
+    assert_eq!(
+        graph.try_add_edge(4, 3, ()),
+        Err(GraphError::MissingSource(4))
+    );
+    assert!(graph.edges_from(&1).is_none());
+    assert!(graph.edges_to(&3).is_none());
+
+    let first = graph.try_add_edge(1, 2, ()).unwrap();
+    let second = graph.add_edge(1, 2, ());
+    assert_ne!(first, second);
+    assert_eq!(graph.edges_from(&1).unwrap()[0].0, first);
+    assert_eq!(graph.edges_to(&2).unwrap()[1].0, second);
+}
+
+#[test]
+fn test_check_integrity() {
+    let graph: Graph<u64, &str> = [(1, "a"), (2, "b"), (3, "c")].into_iter().collect();
+    let one_to_two = graph.add_edge(1, 2, ());
+    let two_to_three = graph.add_edge(2, 3, ());
+    assert!(graph.check_integrity().is_ok());
+
+    // Bypass the public API to corrupt the graph
+    graph.edges.remove(&one_to_two);
+    graph.nodes.remove(&3);
+
+    let err = graph.clone().try_into_read_only().unwrap_err();
+    let dangling = err.dangling();
+    assert_eq!(dangling.len(), 2);
+    assert!(dangling.contains(&Dangling::Edge {
+        edge_id: one_to_two,
+        from: 1,
+        to: 2
+    }));
+    assert!(dangling.contains(&Dangling::Node {
+        key: 3,
+        edge_id: two_to_three
+    }));
+
+    // Adjacency lists that disagree, and edge data nothing refers to
+    let corrupt: Graph<u64, &str> = [(1, "a"), (2, "b")].into_iter().collect();
+    let one_way = corrupt.add_edge(1, 2, ());
+    let orphan = corrupt.add_edge(2, 1, ());
+    corrupt.to.get_mut(&2).unwrap().clear();
+    corrupt.from.get_mut(&2).unwrap().clear();
+    corrupt.to.get_mut(&1).unwrap().clear();
+    let dangling = corrupt.check_integrity().unwrap_err().into_dangling();
+    assert_eq!(
+        dangling,
+        vec![
+            Dangling::Asymmetric {
+                edge_id: one_way,
+                from: 1,
+                to: 2
+            },
+            Dangling::Orphan { edge_id: orphan }
+        ]
+    );
+    assert!(corrupt.try_into_read_only().is_err());
+
+    // Adjacency lists that disagree with the edge's own endpoints
+    let corrupt: Graph<u64, &str> = [(1, "a"), (2, "b")].into_iter().collect();
+    let reversed = corrupt.add_edge(1, 2, ());
+    *corrupt.edges.get_mut(&reversed).unwrap() = (2, (), 1);
+    assert_eq!(
+        corrupt.check_integrity().unwrap_err().into_dangling(),
+        vec![Dangling::Asymmetric {
+            edge_id: reversed,
+            from: 1,
+            to: 2
+        }]
+    );
+
+    let graph = graph.into_read_only();
+    let results: Vec<_> = graph.try_iter().collect();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(Result::is_err));
+    let from_two: Vec<_> = graph.try_iter_edges_from(&2).unwrap().collect();
+    assert_eq!(
+        from_two,
+        vec![Err(Dangling::Node {
+            key: 3,
+            edge_id: two_to_three
+        })]
+    );
+    // Dangling edges are skipped rather than panicking
+    assert_eq!(graph.iter().count(), 0);
+    assert_eq!(graph.iter_edges_from(&2).unwrap().count(), 0);
+
+    // Edges whose source is missing are reported too
+    let graph: Graph<u64, &str> = [(1, "a"), (2, "b")].into_iter().collect();
+    let edge_id = graph.add_edge(1, 2, ());
+    graph.nodes.remove(&1);
+    let graph = graph.into_read_only();
+    assert_eq!(
+        graph.try_iter().collect::<Vec<_>>(),
+        vec![Err(Dangling::Node { key: 1, edge_id })]
+    );
+    assert_eq!(graph.iter().count(), 0);
+}
+
+fn diamond() -> Graph<u32, ()> {
+    let graph: Graph<u32, ()> = (0..5).map(|i| (i, ())).collect();
+    graph.add_edge(0, 1, ());
+    graph.add_edge(0, 2, ());
+    graph.add_edge(1, 3, ());
+    graph.add_edge(2, 3, ());
+    graph
+}
+
+#[test]
+fn test_traversals() {
+    let graph = diamond();
+
+    let bfs: Vec<_> = Bfs::new(&graph, 0).collect();
+    assert_eq!(bfs, vec![0, 1, 2, 3]);
+    let dfs: Vec<_> = Dfs::new(&graph, 0).collect();
+    assert_eq!(dfs, vec![0, 1, 3, 2]);
+    let post: Vec<_> = DfsPostOrder::new(&graph, 0).collect();
+    assert_eq!(post, vec![3, 1, 2, 0]);
+
+    let reversed: Vec<_> = Bfs::new(&graph, 3).direction(Direction::Incoming).collect();
+    assert_eq!(reversed, vec![3, 1, 2, 0]);
+
+    // Missing starts are skipped, and 4 is disconnected
+    let multi: Vec<_> = Dfs::with_starts(&graph, [2, 42, 4]).collect();
+    assert_eq!(multi, vec![2, 3, 4]);
+
+    let graph = graph.into_read_only();
+    let post: Vec<_> = DfsPostOrder::new(&graph, 3)
+        .direction(Direction::Incoming)
+        .collect();
+    assert_eq!(post, vec![0, 1, 2, 3]);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_bfs() {
+    // A binary tree, where node `i` has children `2i + 1` and `2i + 2`
+    let graph: Graph<u32, ()> = (0..1023).map(|i| (i, ())).collect();
+    for i in 0..511 {
+        graph.add_edge(i, 2 * i + 1, ());
+        graph.add_edge(i, 2 * i + 2, ());
+    }
+    // Shortcut from the root to a leaf
+    graph.add_edge(0, 1000, ());
+
+    let tree = graph.par_bfs([0], Direction::Outgoing);
+    assert_eq!(tree.len(), 1023);
+    assert_eq!(tree.distance(&0), Some(0));
+    assert_eq!(tree.parent(&0), None);
+    assert_eq!(tree.distance(&1022), Some(9));
+    assert_eq!(tree.parent(&1022), Some(510));
+    assert_eq!(tree.path_to(&1000), Some(vec![0, 1000]));
+
+    let tree = graph.par_bfs([1000, 1022], Direction::Incoming);
+    assert_eq!(tree.distance(&0), Some(1));
+    assert_eq!(tree.distance(&510), Some(1));
+    assert!(!tree.contains(&1));
+}
+
+#[test]
+fn test_toposort() {
+    let graph = diamond();
+    assert_eq!(graph.toposort_ordered().unwrap(), vec![0, 1, 2, 3, 4]);
+
+    let sorted = graph.toposort().unwrap();
+    let position = |key: u32| sorted.iter().position(|k| *k == key).unwrap();
+    assert_eq!(sorted.len(), 5);
+    assert!(position(0) < position(1));
+    assert!(position(1) < position(3));
+    assert!(position(2) < position(3));
+
+    // 3 -> 4 -> 1 closes the cycle 1 -> 3 -> 4 -> 1
+    graph.add_edge(3, 4, ());
+    graph.add_edge(4, 1, ());
+    let cycle = graph.into_read_only().toposort().unwrap_err().into_path();
+    assert_eq!(cycle.len(), 3);
+    let start = cycle.iter().position(|k| *k == 1).unwrap();
+    let mut rotated = cycle.clone();
+    rotated.rotate_left(start);
+    assert_eq!(rotated, vec![1, 3, 4]);
+
+    let graph: Graph<u32, ()> = [(0, ())].into_iter().collect();
+    graph.add_edge(0, 0, ());
+    assert_eq!(graph.toposort().unwrap_err().path(), &[0]);
+}
+
+#[test]
+fn test_strongly_connected_components() {
+    // Two cycles, 0 <-> 1 and 2 -> 3 -> 4 -> 2, connected by two edges
+    let graph: Graph<u32, ()> = (0..6).map(|i| (i, ())).collect();
+    graph.add_edge(0, 1, ());
+    graph.add_edge(1, 0, ());
+    let first = graph.add_edge(0, 2, ());
+    let second = graph.add_edge(1, 3, ());
+    graph.add_edge(2, 3, ());
+    graph.add_edge(3, 4, ());
+    graph.add_edge(4, 2, ());
+    graph.add_edge(4, 4, ());
+
+    let sccs = graph.into_read_only().strongly_connected_components();
+    assert_eq!(sccs.len(), 3);
+    let upstream = sccs.component_of(&0).unwrap();
+    let downstream = sccs.component_of(&2).unwrap();
+    let isolated = sccs.component_of(&5).unwrap();
+    assert_eq!(sccs.component_of(&1), Some(upstream));
+    assert_eq!(sccs.component_of(&3), Some(downstream));
+    assert_eq!(sccs.component_of(&4), Some(downstream));
+    assert!(upstream < downstream);
+
+    let mut members = sccs.components()[downstream].clone();
+    members.sort_unstable();
+    assert_eq!(members, vec![2, 3, 4]);
+    assert_eq!(sccs.components()[isolated], vec![5]);
+
+    let condensation = sccs.into_condensation();
+    assert_eq!(condensation.len(), 3);
+    assert!(condensation.toposort().is_ok());
+    let edges = condensation.edges_from(&upstream).unwrap();
+    assert_eq!(edges.len(), 1);
+    assert_eq!(edges[0].1, downstream);
+    let mut aggregated = condensation.get_edge(edges[0].0).unwrap().clone();
+    aggregated.sort_unstable_by_key(|id| id.0);
+    assert_eq!(aggregated, vec![first, second]);
+    assert!(condensation.edges_from(&downstream).is_none());
+}
+
+#[test]
+fn test_shortest_paths() {
+    let graph: Graph<u32, (), i32> = (0..5).map(|i| (i, ())).collect();
+    graph.add_edge(0, 1, 4);
+    graph.add_edge(0, 2, 1);
+    let two_to_one = graph.add_edge(2, 1, 2);
+    graph.add_edge(1, 3, 1);
+    graph.add_edge(2, 3, 5);
+
+    let paths = graph.dijkstra(0, |w| *w);
+    assert_eq!(paths.distance(&0), Some(0));
+    assert_eq!(paths.distance(&1), Some(3));
+    assert_eq!(paths.distance(&3), Some(4));
+    assert_eq!(paths.distance(&4), None);
+    assert_eq!(paths.predecessor(&1), Some((&2, two_to_one)));
+    assert_eq!(paths.path_to(&3), Some(vec![0, 2, 1, 3]));
+    assert_eq!(paths.path_to(&4), None);
+
+    let paths = graph.bellman_ford(0, |w| *w).unwrap();
+    assert_eq!(paths.distance(&3), Some(4));
+    assert_eq!(paths.path_to(&3), Some(vec![0, 2, 1, 3]));
+
+    // Negative weights change the result
+    let graph = graph.into_read_only();
+    let paths = graph
+        .bellman_ford(0, |w| if *w == 5 { -5 } else { *w })
+        .unwrap();
+    assert_eq!(paths.distance(&3), Some(-4));
+    assert_eq!(paths.path_to(&3), Some(vec![0, 2, 3]));
+}
+
+#[test]
+fn test_bellman_ford_negative_cycle() {
+    let graph: Graph<u32, (), i32> = (0..4).map(|i| (i, ())).collect();
+    graph.add_edge(0, 1, 1);
+    graph.add_edge(1, 2, -2);
+    graph.add_edge(2, 3, 1);
+    graph.add_edge(3, 1, -1);
+
+    let mut cycle = graph.bellman_ford(0, |w| *w).unwrap_err().into_path();
+    let start = cycle.iter().position(|k| *k == 1).unwrap();
+    cycle.rotate_left(start);
+    assert_eq!(cycle, vec![1, 2, 3]);
+
+    assert!(graph.bellman_ford(3, |w| *w).is_err());
+    assert!(graph.bellman_ford(0, |w| w.abs()).is_ok());
+
+    // The cycle cannot be reached from a disconnected node
+    graph.insert(4, ());
+    assert!(graph.bellman_ford(4, |w| *w).is_ok());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    let graph: Graph<u32, String, i32> = (0..3).map(|i| (i, i.to_string())).collect();
+    let first = graph.add_edge(0, 1, 10);
+    let removed = graph.add_edge(1, 2, 20);
+    let last = graph.add_edge(2, 0, 30);
+    graph.remove_edge(removed);
+
+    let json = serde_json::to_string(&graph).unwrap();
+    let restored: Graph<u32, String, i32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.len(), 3);
+    assert_eq!(restored.get_node(&1).unwrap().value(), "1");
+    assert_eq!(*restored.get_edge(first).unwrap(), 10);
+    assert_eq!(*restored.get_edge(last).unwrap(), 30);
+    assert!(restored.get_edge(removed).is_none());
+    assert_eq!(restored.edges_to(&0).unwrap()[0], (last, 2));
+
+    // New edges don't reuse ids, including ones that were removed
+    let next = restored.add_edge(1, 2, 40);
+    assert!(next.0 > last.0);
+
+    let dropped = restored.add_edge(2, 1, 50);
+    restored.remove_edge(dropped);
+
+    let read_only = restored.into_read_only();
+    let json = serde_json::to_string(&read_only).unwrap();
+    let restored: crate::ReadOnlyGraph<u32, String, i32, std::hash::RandomState> =
+        serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.get_edge(next), Some(&40));
+    assert_eq!(restored.edges_from(&1).unwrap(), &vec![(next, 2)]);
+    assert!(restored.into_graph().add_edge(0, 1, 60).0 > dropped.0);
+
+    // Edges must connect existing nodes
+    let invalid = r#"{"nodes":[[0,"0"]],"edges":[[0,0,1,5]],"next_edge_id":1}"#;
+    assert!(serde_json::from_str::<Graph<u32, String, i32>>(invalid).is_err());
+}
+
+#[cfg(feature = "snapshot")]
+#[test]
+fn test_snapshot_round_trip() {
+    use crate::{ReadOnlyGraph, SnapshotError};
+    type Snapshot = ReadOnlyGraph<u32, String, Option<i64>, std::hash::RandomState>;
+
+    // Enough nodes to span several encoding chunks
+    let graph: Graph<u32, String, Option<i64>> = (0..10_000).map(|i| (i, i.to_string())).collect();
+    for i in 0..10_000 {
+        graph.add_edge(i, (i + 1) % 10_000, Some(i as i64));
+        graph.add_edge(i, (i * 7) % 10_000, None);
+    }
+    let removed = graph.add_edge(0, 1, None);
+    graph.remove_edge(removed);
+    let graph = graph.into_read_only();
+
+    let mut snapshot = Vec::new();
+    graph.write_snapshot(&mut snapshot).unwrap();
+    let loaded = Snapshot::read_snapshot(&snapshot[..]).unwrap();
+
+    assert_eq!(loaded.len(), graph.len());
+    assert!(loaded.get_edge(removed).is_none());
+    for (key, value) in graph.iter_nodes() {
+        assert_eq!(loaded.get_node(key), Some(value));
+        assert_eq!(loaded.edges_from(key), graph.edges_from(key));
+        assert_eq!(loaded.edges_to(key), graph.edges_to(key));
+        for (edge_id, _) in graph.edges_from(key).unwrap() {
+            assert_eq!(loaded.get_edge(*edge_id), graph.get_edge(*edge_id));
+            assert_eq!(
+                loaded.edge_endpoints(*edge_id),
+                graph.edge_endpoints(*edge_id)
+            );
+        }
+    }
+    assert!(loaded.edge_endpoints(removed).is_none());
+
+    let load = |bytes: &[u8]| Snapshot::from_snapshot_bytes(bytes);
+    assert!(matches!(
+        load(b"not a snapshot"),
+        Err(SnapshotError::BadMagic)
+    ));
+    let mut future = snapshot.clone();
+    future[8] = 2;
+    assert!(matches!(
+        load(&future),
+        Err(SnapshotError::UnsupportedVersion(2))
+    ));
+    assert!(load(&snapshot[..snapshot.len() - 1]).is_err());
+
+    // Orphan the first node's edges by moving the start of the edge offsets
+    let read_u64 = |at: usize| u64::from_le_bytes(snapshot[at..at + 8].try_into().unwrap());
+    let node_count = read_u64(16) as usize;
+    let node_data_len = read_u64(32 + node_count * 8) as usize;
+    let edge_offsets = 32 + (node_count + 1) * 8 + node_data_len;
+    let mut orphaned = snapshot.clone();
+    orphaned[edge_offsets] = 1;
+    assert!(matches!(
+        load(&orphaned),
+        Err(SnapshotError::Corrupt("edge offsets out of order"))
+    ));
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mapped_graph() {
+    use std::{fs::File, io::BufWriter};
+
+    use crate::{mmap::MappedGraph, SnapshotError};
+
+    let graph: Graph<u64, [u8; 3], f64> = (0..1_000).map(|i| (i * 3, [i as u8; 3])).collect();
+    for i in 0..1_000 {
+        graph.add_edge(i * 3, ((i + 1) % 1_000) * 3, i as f64);
+        graph.add_edge(i * 3, ((i * 7) % 1_000) * 3, -(i as f64));
+    }
+    let removed = graph.add_edge(0, 3, 0.0);
+    graph.remove_edge(removed);
+    let graph = graph.into_read_only();
+
+    let path = std::env::temp_dir().join(format!("carpet-test-{}.bin", std::process::id()));
+    let file = File::create(&path).unwrap();
+    graph.write_mapped(BufWriter::new(file)).unwrap();
+    // SAFETY: the file is private to this test
+    let mapped = unsafe { MappedGraph::<u64, [u8; 3], f64>::open(&path) }.unwrap();
+    mapped.validate().unwrap();
+
+    assert_eq!(mapped.len(), 1_000);
+    assert_eq!(mapped.edge_count(), 2_000);
+    assert!(!mapped.has_node(&1));
+    assert!(mapped.get_edge(removed).is_none());
+    for (key, value) in graph.iter_nodes() {
+        assert_eq!(mapped.get_node(key), Some(value));
+        let expected: Vec<_> = graph
+            .edges_from(key)
+            .unwrap()
+            .iter()
+            .map(|(edge_id, to)| (*edge_id, to))
+            .collect();
+        let actual: Vec<_> = mapped.edge_ids_from(key).unwrap().collect();
+        assert_eq!(actual, expected);
+        for (edge_id, _) in expected {
+            assert_eq!(mapped.get_edge(edge_id), graph.get_edge(edge_id));
+        }
+        assert!(mapped
+            .iter_edges_from(key)
+            .unwrap()
+            .eq(graph.iter_edges_from(key).unwrap()));
+    }
+    assert!(mapped
+        .iter_nodes()
+        .map(|(key, _)| *key)
+        .eq((0..1_000).map(|i| i * 3)));
+
+    // The layout of every type must match
+    assert!(matches!(
+        unsafe { MappedGraph::<u64, [u8; 4], f64>::open(&path) },
+        Err(SnapshotError::Corrupt(_))
+    ));
+
+    // Opening only checks the header, so unsorted edge ids are caught by
+    // `validate`. The sorted edge ids are the second to last section, and
+    // 2,000 `u64`s fill their sections exactly.
+    drop(mapped);
+    let mut bytes = std::fs::read(&path).unwrap();
+    let sorted = bytes.len() - 32_000;
+    bytes[sorted..sorted + 16].rotate_left(8);
+    std::fs::write(&path, &bytes).unwrap();
+    let mapped = unsafe { MappedGraph::<u64, [u8; 3], f64>::open(&path) }.unwrap();
+    assert!(matches!(
+        mapped.validate(),
+        Err(SnapshotError::Corrupt("edge ids are not sorted"))
+    ));
+
+    // The byte order must match too
+    drop(mapped);
+    bytes[12..16].reverse();
+    std::fs::write(&path, bytes).unwrap();
+    assert!(matches!(
+        unsafe { MappedGraph::<u64, [u8; 3], f64>::open(&path) },
+        Err(SnapshotError::Corrupt(_))
+    ));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_freeze_csr() {
+    let graph: Graph<u32, &str, u32> = (0..4)
+        .map(|i| (i, ["a", "b", "c", "d"][i as usize]))
+        .collect();
+    let ab = graph.add_edge(0, 1, 1);
+    let ac = graph.add_edge(0, 2, 2);
+    let bd = graph.add_edge(1, 3, 3);
+    let cd = graph.add_edge(2, 3, 4);
+    let removed = graph.add_edge(3, 0, 5);
+    graph.remove_edge(removed);
+
+    let csr = graph.freeze_csr();
+    assert_eq!(csr.len(), 4);
+    assert_eq!(csr.edge_count(), 4);
+    assert_eq!(csr.get_node(&2), Some(&"c"));
+    assert_eq!(csr.get_edge(cd), Some(&4));
+    assert!(csr.get_edge(removed).is_none());
+
+    let [a, b, c, d] = [0, 1, 2, 3].map(|key| csr.index_of(&key).unwrap());
+    assert_eq!(csr.key(d), &3);
+    assert_eq!(csr.out_neighbors(a), &[b, c]);
+    assert!(csr.out_neighbors(d).is_empty());
+    assert!(csr.in_neighbors(a).is_empty());
+    let mut incoming: Vec<_> = csr.in_edges(d).collect();
+    incoming.sort_by_key(|(edge_id, ..)| edge_id.0);
+    assert_eq!(incoming, vec![(bd, &3, b), (cd, &4, c)]);
+    assert!(csr.out_edges(a).eq([(ab, &1, b), (ac, &2, c)]));
+
+    // Algorithms run over frozen graphs too
+    let paths = crate::algo::dijkstra(&csr, 0, |weight| *weight);
+    assert_eq!(paths.distance(&3), Some(4));
+    assert_eq!(Bfs::new(&csr, 3).direction(Direction::Incoming).count(), 4);
+}
+
+#[test]
+fn test_into_graph() {
+    let mut graph = diamond();
+    graph.enable_edge_index();
+    let kept = graph.add_edge(0, 4, ());
+    let removed = graph.add_edge(3, 4, ());
+    graph.remove_edge(removed);
+
+    let graph: Graph<u32, ()> = graph.into_read_only().into();
+    assert_eq!(graph.len(), 5);
+    assert_eq!(graph.edges_from(&0).unwrap().len(), 3);
+    assert_eq!(graph.edges_to(&3).unwrap().len(), 2);
+    assert!(graph.has_edge_index());
+    assert_eq!(graph.find_edge(&0, &4), Some(kept));
+
+    // New edges never reuse the ids of existing or removed edges
+    let added = graph.add_edge(4, 0, ());
+    assert!(added.0 > removed.0);
+    assert_eq!(graph.find_edge(&4, &0), Some(added));
+    assert!(graph.check_integrity().is_ok());
+
+    // The round trip can be repeated
+    let graph = graph.into_read_only().into_graph();
+    assert!(graph.get_edge(added).is_some());
+    graph.remove_node(&4);
+    assert!(graph.get_edge(kept).is_none());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_iter_edges() {
+    use rayon::prelude::*;
+
+    let graph: Graph<u32, (), u32> = (0..100).map(|i| (i, ())).collect();
+    for i in 0..100 {
+        graph.add_edge(i, (i + 1) % 100, i);
+        graph.add_edge(i, (i + 2) % 100, 100 + i);
+    }
+    let removed = graph.add_edge(0, 50, 1_000);
+    graph.remove_edge(removed);
+
+    let mut edges: Vec<_> = graph
+        .par_iter_edges()
+        .map(|(from, edge_id, to)| (from, edge_id.0, *graph.get_edge(edge_id).unwrap(), to))
+        .collect();
+    edges.sort_unstable();
+    assert_eq!(edges.len(), 200);
+    assert_eq!(
+        graph
+            .par_iter_adjacency()
+            .map(|list| list.len())
+            .sum::<usize>(),
+        200
+    );
+
+    let graph = graph.into_read_only();
+    let mut read_only_edges: Vec<_> = graph
+        .par_iter_edges()
+        .map(|(from, edge_id, weight, to)| (*from, edge_id.0, *weight, *to))
+        .collect();
+    read_only_edges.sort_unstable();
+    assert_eq!(read_only_edges, edges);
+    assert_eq!(graph.par_iter_adjacency().count(), 100);
+
+    // Edges can be written to while iterating, without deadlocking
+    let graph = graph.into_graph();
+    graph.par_iter_edges().for_each(|(from, edge_id, _)| {
+        graph.update_edge(edge_id, |weight| *weight += 1);
+        graph.add_edge(from, from, 0);
+    });
+    assert_eq!(graph.par_iter_edges().count(), 400);
+    let total: u32 = edges
+        .iter()
+        .map(|(_, id, _, _)| *graph.get_edge(EdgeId(*id)).unwrap())
+        .sum();
+    assert_eq!(total, (0..200).sum::<u32>() + 200);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_read_only_par_iter() {
+    use rayon::prelude::*;
+
+    let graph = diamond();
+    graph.add_edge(3, 4, ());
+    let graph = graph.into_read_only();
+
+    let mut keys: Vec<u32> = (&graph).into_par_iter().map(|(key, _)| *key).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, vec![0, 1, 2, 3, 4]);
+    assert_eq!(graph.par_iter_nodes().count(), 5);
+    assert_eq!(graph.par_iter().count(), graph.iter().count());
+
+    // Dangling edges are skipped instead of panicking
+    let graph = diamond();
+    let edge_id = graph.edges_from(&0).unwrap()[1].0;
+    graph.edges.remove(&edge_id);
+    graph.nodes.remove(&3);
+    let graph = graph.into_read_only();
+    assert_eq!(graph.par_iter().count(), 1);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_bulk_load() {
+    use rayon::prelude::*;
+
+    let users: UserGraph = (0..1_000)
+        .into_par_iter()
+        .map(|id| User {
+            id,
+            name: id.to_string(),
+        })
+        .collect();
+    assert_eq!(users.get_node(&10).unwrap().name, "10");
+
+    let mut graph: Graph<u32, ()> = (0..5_000).into_par_iter().map(|i| (i, ())).collect();
+    graph.par_extend((5_000..10_000).into_par_iter().map(|i| (i, ())));
+    assert_eq!(graph.len(), 10_000);
+
+    let first = graph.add_edge(0, 0, ());
+    graph.remove_edge(first);
+    graph.par_add_edges((0..20_000).into_par_iter().map(|j| {
+        let i = j / 2;
+        (i, (i + [1, 7][j as usize % 2]) % 10_000, ())
+    }));
+    assert!(graph.check_integrity().is_ok());
+    assert!((0..10_000).all(|i| graph.edges_from(&i).unwrap().len() == 2));
+
+    // Every edge got the next id in order, and later edges don't reuse them
+    let mut ids: Vec<u64> = graph.par_iter_edges().map(|(_, id, _)| id.0).collect();
+    ids.sort_unstable();
+    assert!(ids.iter().copied().eq(first.0 + 1..first.0 + 20_001));
+    assert!(graph.add_edge(0, 1, ()).0 > *ids.last().unwrap());
+}
+
+#[test]
+fn test_extend_and_from_edges() {
+    let mut graph: Graph<u32, &str> = Graph::from_edges([(0, 1, ()), (1, 2, ()), (2, 0, ())]);
+    assert_eq!(graph.len(), 3);
+    assert_eq!(*graph.get_node(&1).unwrap(), "");
+    assert_eq!(graph.toposort().unwrap_err().path().len(), 3);
+
+    // Existing nodes are overwritten, and their edges are kept
+    graph.extend([(1, "one"), (3, "three")]);
+    assert_eq!(graph.len(), 4);
+    assert_eq!(*graph.get_node(&1).unwrap(), "one");
+    assert_eq!(graph.edges_from(&1).unwrap().len(), 1);
+
+    let mut users = UserGraph::default();
+    users.extend((1..=2).map(|id| User {
+        id,
+        name: format!("user {id}"),
+    }));
+    assert_eq!(users.get_node(&2).unwrap().name, "user 2");
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_entry() {
+    use rayon::prelude::*;
+
+    let graph: Graph<u32, u32> = Graph::new();
+    (0..10_000).into_par_iter().for_each(|i| {
+        graph
+            .entry(i % 10)
+            .and_modify(|count| *count += 1)
+            .or_insert(1);
+    });
+    assert_eq!(graph.len(), 10);
+    assert!(graph.iter_nodes().all(|node| *node.value() == 1_000));
+
+    graph.add_edge(0, 1, ());
+    let entry = graph.entry(0);
+    assert!(entry.is_occupied());
+    assert_eq!(*entry.insert(5), 5);
+    assert_eq!(graph.edges_from(&0).unwrap().len(), 1);
+    assert_eq!(*graph.entry(42).or_default(), 0);
+    assert_eq!(*graph.entry(43).or_insert_with(|| 7), 7);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_update_edge() {
+    use rayon::prelude::*;
+
+    let graph: Graph<u32, (), u64> = (0..2).map(|i| (i, ())).collect();
+    let edge_id = graph.add_edge(0, 1, 0);
+    (0..1_000).into_par_iter().for_each(|_| {
+        graph.update_edge(edge_id, |count| *count += 1).unwrap();
+    });
+    assert_eq!(*graph.get_edge(edge_id).unwrap(), 1_000);
+
+    *graph.get_edge_mut(edge_id).unwrap() = 0;
+    assert_eq!(*graph.get_edge(edge_id).unwrap(), 0);
+
+    graph.remove_edge(edge_id);
+    assert!(graph.get_edge_mut(edge_id).is_none());
+    assert_eq!(graph.update_edge(edge_id, |count| *count), None);
+}
+
+#[test]
+fn test_with_node_and_neighbors() {
+    let graph = diamond();
+    let degrees = graph.with_node_and_neighbors(&3, |_, outgoing, incoming| {
+        // Reading other parts of the graph is fine, even from the same shards
+        assert!(graph.has_node(&3));
+        assert!(incoming
+            .iter()
+            .all(|(_, from)| graph.edges_from(from).is_some()));
+        (outgoing.len(), incoming.len())
+    });
+    assert_eq!(degrees, Some((0, 2)));
+    assert_eq!(graph.with_node_and_neighbors(&42, |_, _, _| ()), None);
+
+    // Locks are released once the closure returns
+    graph.add_edge(3, 4, ());
+    graph.remove_node(&3);
+}
+
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+#[test]
+#[should_panic(expected = "deadlock detected")]
+fn test_deadlock_detector() {
+    let graph = diamond();
+    graph.with_node_and_neighbors(&0, |_, _, _| {
+        graph.add_edge(0, 4, ());
+    });
+}
+
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+#[test]
+#[should_panic(expected = "deadlock detected")]
+fn test_deadlock_detector_remove_edge() {
+    let graph = diamond();
+    let edge_id = graph.find_edge(&0, &1).unwrap();
+    graph.with_node_and_neighbors(&1, |_, _, incoming| {
+        assert_eq!(incoming[0].0, edge_id);
+        graph.remove_edge(edge_id);
+    });
+}
+
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+#[test]
+#[should_panic(expected = "deadlock detected")]
+fn test_deadlock_detector_guards() {
+    // The scenario from the README: holding a read guard, then writing to the
+    // same node on the same thread
+    let graph = diamond();
+    let node = graph.get_node(&0).unwrap();
+    graph.get_node_mut(&0);
+    drop(node);
+}
+
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+#[test]
+#[should_panic(expected = "deadlock detected")]
+fn test_deadlock_detector_edges() {
+    let graph: Graph<u32, (), u32> = (0..2).map(|i| (i, ())).collect();
+    let edge_id = graph.add_edge(0, 1, 0);
+    let edge = graph.get_edge(edge_id).unwrap();
+    graph.update_edge(edge_id, |weight| *weight += *edge);
+}
+
+#[cfg(all(debug_assertions, feature = "deadlock-detection"))]
+#[test]
+fn test_tracked_guards() {
+    let graph = diamond();
+    {
+        // Guards on different nodes, or reads of the same node, are fine
+        let node = graph.get_node(&0).unwrap();
+        let outgoing = graph.edges_from(&0).unwrap();
+        assert!(graph.has_node(&0));
+        assert_eq!((node.key(), outgoing.len()), (&0, 2));
+    }
+    // Guards are released once dropped, even on another thread
+    let node = graph.get_node_mut(&0).unwrap();
+    std::thread::scope(|scope| {
+        scope.spawn(move || drop(node));
+    });
+    graph.remove_node(&0);
+    assert!(!graph.has_node(&0));
+}
+
+#[test]
+fn test_neighbors() {
+    let graph: Graph<u32, &str> = (0..4)
+        .map(|i| (i, ["a", "b", "c", "d"][i as usize]))
+        .collect();
+    let ab = graph.add_edge(0, 1, ());
+    graph.add_edge(0, 2, ());
+    graph.add_edge(2, 1, ());
+    graph.add_edge(0, 3, ());
+    graph.remove_node(&3);
+
+    assert_eq!(graph.out_degree(&0), 2);
+    assert_eq!(graph.in_degree(&1), 2);
+    assert_eq!(graph.in_degree(&42), 0);
+    let successors: Vec<&str> = graph.successors(&0).map(|(_, node)| *node).collect();
+    assert_eq!(successors, vec!["b", "c"]);
+    assert_eq!(graph.predecessors(&42).count(), 0);
+
+    // Owned snapshots don't hold any locks
+    let owned = graph.neighbors_owned(&1, Direction::Incoming);
+    assert_eq!(owned, vec![(ab, 0, "a"), (owned[1].0, 2, "c")]);
+    for (_, key, _) in &owned {
+        *graph.get_node_mut(key).unwrap() = "updated";
+    }
+
+    let graph = graph.into_read_only();
+    let predecessors: Vec<_> = graph
+        .predecessors(&1)
+        .map(|(_, key, value)| (*key, *value))
+        .collect();
+    assert_eq!(predecessors, vec![(0, "updated"), (2, "updated")]);
+    assert_eq!(graph.successors(&0).count(), 2);
+    assert_eq!(graph.out_degree(&1), 0);
+    assert_eq!(graph.in_degree(&2), 1);
+}
+
+#[test]
+fn test_edges_directed_and_reversed() {
+    use crate::{algo, visit::Reversed};
+
+    let graph = diamond();
+    for key in 0..4 {
+        assert_eq!(
+            graph.edges_from(&key).map(|edges| edges.clone()),
+            graph
+                .edges_directed(&key, Direction::Outgoing)
+                .map(|edges| edges.clone())
+        );
+        assert_eq!(
+            graph.edges_to(&key).map(|edges| edges.clone()),
+            graph
+                .edges_directed(&key, Direction::Incoming)
+                .map(|edges| edges.clone())
+        );
+    }
+
+    // Walking the transpose forwards is walking the graph backwards
+    let reversed = graph.reversed();
+    let ancestors: Vec<_> = Dfs::new(&reversed, 3).collect();
+    let expected: Vec<_> = Dfs::new(&graph, 3).direction(Direction::Incoming).collect();
+    assert_eq!(ancestors, expected);
+    let order = algo::toposort(&reversed).unwrap();
+    let position = |key| order.iter().position(|k| *k == key).unwrap();
+    assert!(position(3) < position(1) && position(1) < position(0));
+    assert_eq!(
+        Dfs::new(&Reversed::new(&reversed), 0).collect::<Vec<_>>(),
+        Dfs::new(&graph, 0).collect::<Vec<_>>()
+    );
+
+    let graph = graph.into_read_only();
+    assert_eq!(
+        graph.edges_directed(&3, Direction::Incoming).unwrap().len(),
+        2
+    );
+    assert!(graph.edges_directed(&3, Direction::Outgoing).is_none());
+    for key in 0..4 {
+        assert_eq!(
+            graph.edges_directed(&key, Direction::Outgoing),
+            graph.edges_from(&key)
+        );
+        assert_eq!(
+            graph.edges_directed(&key, Direction::Incoming),
+            graph.edges_to(&key)
+        );
+    }
+    let roots: Vec<_> = Dfs::new(&graph.reversed(), 3).collect();
+    assert!(roots.contains(&0));
+}
+
+#[test]
+fn test_find_edge() {
+    let mut graph = diamond();
+    let parallel = graph.add_edge(0, 1, ());
+    let loop_ = graph.add_edge(3, 3, ());
+
+    for indexed in [false, true] {
+        if indexed {
+            graph.enable_edge_index();
+        }
+        assert_eq!(graph.has_edge_index(), indexed);
+        let first = graph.edges_from(&0).unwrap()[0].0;
+        assert_eq!(graph.find_edge(&0, &1), Some(first));
+        assert_eq!(graph.edges_connecting(&0, &1), vec![first, parallel]);
+        assert_eq!(graph.find_edge(&3, &3), Some(loop_));
+        assert!(graph.contains_edge(&2, &3));
+        assert!(!graph.contains_edge(&3, &2));
+        assert!(!graph.contains_edge(&0, &42));
+        assert!(graph.edges_connecting(&4, &0).is_empty());
+    }
+
+    // The index follows removals
+    let first = graph.find_edge(&0, &1).unwrap();
+    graph.remove_edge(first);
+    assert_eq!(graph.edges_connecting(&0, &1), vec![parallel]);
+    graph.remove_node(&3);
+    assert!(!graph.contains_edge(&1, &3));
+    assert!(!graph.contains_edge(&3, &3));
+    // Sources without edges are dropped from the index
+    let index = graph.edge_index.as_ref().unwrap();
+    assert!(!index.contains_key(&1) && !index.contains_key(&2));
+    let edge_id = graph.add_edge(2, 1, ());
+    assert_eq!(graph.find_edge(&2, &1), Some(edge_id));
+    graph.shrink_all_to_fit();
+    assert_eq!(graph.find_edge(&2, &1), Some(edge_id));
+    assert_eq!(graph.edge_endpoints(edge_id), Some((2, 1)));
+
+    let cloned = graph.clone();
+    assert_eq!(cloned.find_edge(&2, &1), Some(edge_id));
+    graph.disable_edge_index();
+    assert_eq!(graph.find_edge(&2, &1), Some(edge_id));
+
+    let graph = graph.into_read_only();
+    assert_eq!(graph.find_edge(&0, &1), Some(parallel));
+    assert_eq!(graph.edges_connecting(&0, &2).count(), 1);
+    assert!(!graph.contains_edge(&1, &0));
+    assert!(!graph.contains_edge(&42, &0));
+}
+
+#[test]
+fn test_edge_endpoints() {
+    let graph = diamond();
+    let edge_ids: Vec<EdgeId> = graph
+        .edges_from(&0)
+        .unwrap()
+        .iter()
+        .map(|(id, _)| *id)
+        .collect();
+    assert_eq!(graph.edge_endpoints(edge_ids[0]), Some((0, 1)));
+    assert_eq!(graph.edge_endpoints(edge_ids[1]), Some((0, 2)));
+
+    // Removing a node forgets its edges' endpoints
+    let into_three: Vec<EdgeId> = graph
+        .edges_to(&3)
+        .unwrap()
+        .iter()
+        .map(|(id, _)| *id)
+        .collect();
+    graph.remove_node(&3);
+    for edge_id in into_three {
+        assert_eq!(graph.edge_endpoints(edge_id), None);
+    }
+
+    assert_eq!(graph.remove_edge(edge_ids[0]), Some((0, (), 1)));
+    assert_eq!(graph.edge_endpoints(edge_ids[0]), None);
+    assert_eq!(graph.remove_edge(edge_ids[0]), None);
+    assert!(graph.edges_to(&1).unwrap().is_empty());
+    graph.check_integrity().unwrap();
+
+    let graph = graph.into_read_only();
+    assert_eq!(graph.edge_endpoints(edge_ids[1]), Some((&0, &2)));
+    assert_eq!(graph.edge_endpoints(edge_ids[0]), None);
+    let graph = graph.into_graph();
+    assert_eq!(graph.edge_endpoints(edge_ids[1]), Some((0, 2)));
+}
@@ -0,0 +1,698 @@
+//! Graph traversal.
+//!
+//! Traversals walk a graph's adjacency lists, yielding node keys. Keys are
+//! cloned out of adjacency lists, so no locks are held between calls to
+//! [`Iterator::next`]. This makes it safe to mutate a [`Graph`] while
+//! traversing it, although concurrent changes may or may not be observed.
+//!
+//! ```
+//! use carpet::{visit::Bfs, Direction, Graph};
+//!
+//! let graph: Graph<u32, ()> = (0..4).map(|i| (i, ())).collect();
+//! graph.add_edge(0, 1, ());
+//! graph.add_edge(1, 2, ());
+//! graph.add_edge(3, 2, ());
+//!
+//! let reachable: Vec<u32> = Bfs::new(&graph, 0).collect();
+//! assert_eq!(reachable, vec![0, 1, 2]);
+//!
+//! // Walk edges backwards
+//! let ancestors: Vec<u32> = Bfs::new(&graph, 2).direction(Direction::Incoming).collect();
+//! assert_eq!(ancestors.len(), 4);
+//! ```
+use std::{
+    collections::{HashSet, VecDeque},
+    hash::{BuildHasher, Hash},
+};
+
+#[cfg(feature = "rayon")]
+use std::fmt::{self, Debug};
+
+#[cfg(feature = "rayon")]
+use dashmap::{mapref::entry::Entry, DashMap};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{CsrGraph, Direction, EdgeId, Graph, ReadOnlyGraph};
+
+/// Graphs whose nodes and adjacency lists can be walked by the traversals in
+/// this module and the algorithms in [`algo`](crate::algo).
+pub trait Adjacency {
+    type Key: Eq + Hash + Clone;
+
+    /// Returns the number of nodes in the graph.
+    fn node_count(&self) -> usize;
+
+    /// Returns `true` if the graph contains a node with the given `key`.
+    fn contains_node(&self, key: &Self::Key) -> bool;
+
+    /// Calls `f` with the key of every node in the graph, in no particular order.
+    ///
+    /// Implementations may hold a lock while `f` runs, so `f` must not write
+    /// to the graph.
+    fn for_each_node<F>(&self, f: F)
+    where
+        F: FnMut(&Self::Key);
+
+    /// Calls `f` with the id and opposite endpoint of every edge incident to
+    /// `key` in the given `direction`.
+    ///
+    /// Implementations may hold a lock while `f` runs, so `f` must not write
+    /// to the graph.
+    fn for_each_neighbor<F>(&self, key: &Self::Key, direction: Direction, f: F)
+    where
+        F: FnMut(EdgeId, &Self::Key);
+}
+
+impl<K, V, E, S> Adjacency for Graph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    type Key = K;
+
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn contains_node(&self, key: &K) -> bool {
+        self.nodes.contains_key(key)
+    }
+
+    fn for_each_node<F>(&self, mut f: F)
+    where
+        F: FnMut(&K),
+    {
+        for node in self.nodes.iter() {
+            f(node.key());
+        }
+    }
+
+    fn for_each_neighbor<F>(&self, key: &K, direction: Direction, mut f: F)
+    where
+        F: FnMut(EdgeId, &K),
+    {
+        if let Some(edges) = self.edges_directed(key, direction) {
+            for (edge_id, neighbor) in edges.iter() {
+                f(*edge_id, neighbor);
+            }
+        }
+    }
+}
+
+impl<K, V, E, S> Adjacency for ReadOnlyGraph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    type Key = K;
+
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn contains_node(&self, key: &K) -> bool {
+        self.nodes.contains_key(key)
+    }
+
+    fn for_each_node<F>(&self, mut f: F)
+    where
+        F: FnMut(&K),
+    {
+        for key in self.nodes.keys() {
+            f(key);
+        }
+    }
+
+    fn for_each_neighbor<F>(&self, key: &K, direction: Direction, mut f: F)
+    where
+        F: FnMut(EdgeId, &K),
+    {
+        for (edge_id, neighbor) in self.edges_directed(key, direction).into_iter().flatten() {
+            f(*edge_id, neighbor);
+        }
+    }
+}
+
+impl<K, V, E, S> Adjacency for CsrGraph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    type Key = K;
+
+    fn node_count(&self) -> usize {
+        self.len()
+    }
+
+    fn contains_node(&self, key: &K) -> bool {
+        self.has_node(key)
+    }
+
+    fn for_each_node<F>(&self, f: F)
+    where
+        F: FnMut(&K),
+    {
+        self.keys().iter().for_each(f);
+    }
+
+    fn for_each_neighbor<F>(&self, key: &K, direction: Direction, mut f: F)
+    where
+        F: FnMut(EdgeId, &K),
+    {
+        let Some(index) = self.index_of(key) else {
+            return;
+        };
+        match direction {
+            Direction::Outgoing => {
+                for (edge_id, _, to) in self.out_edges(index) {
+                    f(edge_id, self.key(to));
+                }
+            }
+            Direction::Incoming => {
+                for (edge_id, _, from) in self.in_edges(index) {
+                    f(edge_id, self.key(from));
+                }
+            }
+        }
+    }
+}
+
+/// Graphs that can look up the data stored on their edges.
+pub trait EdgeData: Adjacency {
+    type Edge;
+
+    /// Calls `f` with the data of the edge with the given id, returning its
+    /// result. Returns `None` if there is no such edge.
+    fn with_edge<R, F>(&self, edge_id: EdgeId, f: F) -> Option<R>
+    where
+        F: FnOnce(&Self::Edge) -> R;
+}
+
+impl<K, V, E, S> EdgeData for Graph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    type Edge = E;
+
+    fn with_edge<R, F>(&self, edge_id: EdgeId, f: F) -> Option<R>
+    where
+        F: FnOnce(&E) -> R,
+    {
+        self.edges.get(&edge_id).map(|edge| f(&edge.1))
+    }
+}
+
+impl<K, V, E, S> EdgeData for ReadOnlyGraph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    type Edge = E;
+
+    fn with_edge<R, F>(&self, edge_id: EdgeId, f: F) -> Option<R>
+    where
+        F: FnOnce(&E) -> R,
+    {
+        self.get_edge(edge_id).map(f)
+    }
+}
+
+impl<K, V, E, S> EdgeData for CsrGraph<K, V, E, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    type Edge = E;
+
+    fn with_edge<R, F>(&self, edge_id: EdgeId, f: F) -> Option<R>
+    where
+        F: FnOnce(&E) -> R,
+    {
+        self.get_edge(edge_id).map(f)
+    }
+}
+
+/// A view of a graph with the direction of every edge reversed.
+///
+/// Traversals and algorithms run on a [`Reversed`] view work on the
+/// transpose of the underlying graph, without copying it.
+///
+/// # Example
+/// ```
+/// use carpet::{algo, Graph};
+///
+/// let graph: Graph<u32, ()> = (0..3).map(|i| (i, ())).collect();
+/// graph.add_edge(0, 1, ());
+/// graph.add_edge(1, 2, ());
+///
+/// assert_eq!(algo::toposort(&graph.reversed()).unwrap(), vec![2, 1, 0]);
+/// ```
+#[derive(Debug)]
+pub struct Reversed<'g, G> {
+    graph: &'g G,
+}
+
+impl<'g, G> Reversed<'g, G> {
+    /// Create a reversed view of `graph`.
+    pub fn new(graph: &'g G) -> Self {
+        Self { graph }
+    }
+
+    /// Returns the underlying graph.
+    pub fn into_inner(self) -> &'g G {
+        self.graph
+    }
+}
+
+impl<G> Clone for Reversed<'_, G> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<G> Copy for Reversed<'_, G> {}
+
+impl<G: Adjacency> Adjacency for Reversed<'_, G> {
+    type Key = G::Key;
+
+    fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    fn contains_node(&self, key: &G::Key) -> bool {
+        self.graph.contains_node(key)
+    }
+
+    fn for_each_node<F>(&self, f: F)
+    where
+        F: FnMut(&G::Key),
+    {
+        self.graph.for_each_node(f);
+    }
+
+    fn for_each_neighbor<F>(&self, key: &G::Key, direction: Direction, f: F)
+    where
+        F: FnMut(EdgeId, &G::Key),
+    {
+        self.graph.for_each_neighbor(key, direction.opposite(), f);
+    }
+}
+
+impl<G: EdgeData> EdgeData for Reversed<'_, G> {
+    type Edge = G::Edge;
+
+    fn with_edge<R, F>(&self, edge_id: EdgeId, f: F) -> Option<R>
+    where
+        F: FnOnce(&G::Edge) -> R,
+    {
+        self.graph.with_edge(edge_id, f)
+    }
+}
+
+impl<K, V, E, S> Graph<K, V, E, S> {
+    /// Returns a view of the graph with the direction of every edge reversed.
+    /// See [`Reversed`].
+    pub fn reversed(&self) -> Reversed<'_, Self> {
+        Reversed::new(self)
+    }
+}
+
+impl<K, V, E, S> ReadOnlyGraph<K, V, E, S> {
+    /// Returns a view of the graph with the direction of every edge reversed.
+    /// See [`Reversed`].
+    pub fn reversed(&self) -> Reversed<'_, Self> {
+        Reversed::new(self)
+    }
+}
+
+impl<K, V, E, S> CsrGraph<K, V, E, S> {
+    /// Returns a view of the graph with the direction of every edge reversed.
+    /// See [`Reversed`].
+    pub fn reversed(&self) -> Reversed<'_, Self> {
+        Reversed::new(self)
+    }
+}
+
+/// A breadth-first traversal.
+///
+/// Nodes are yielded in the order they are discovered. Start keys that are
+/// not in the graph are skipped.
+pub struct Bfs<'g, G: Adjacency> {
+    graph: &'g G,
+    queue: VecDeque<G::Key>,
+    discovered: HashSet<G::Key>,
+    direction: Direction,
+}
+
+impl<'g, G: Adjacency> Bfs<'g, G> {
+    /// Create a breadth-first traversal starting at `start`.
+    pub fn new(graph: &'g G, start: G::Key) -> Self {
+        Self::with_starts(graph, [start])
+    }
+
+    /// Create a breadth-first traversal starting at each key in `starts`.
+    pub fn with_starts<I>(graph: &'g G, starts: I) -> Self
+    where
+        I: IntoIterator<Item = G::Key>,
+    {
+        let mut discovered = HashSet::new();
+        let queue = starts
+            .into_iter()
+            .filter(|start| graph.contains_node(start) && discovered.insert(start.clone()))
+            .collect();
+
+        Self {
+            graph,
+            queue,
+            discovered,
+            direction: Direction::Outgoing,
+        }
+    }
+
+    /// Follow edges in the given `direction`. Traversals walk
+    /// [outgoing](Direction::Outgoing) edges by default.
+    #[must_use]
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+impl<'g, G: Adjacency> Iterator for Bfs<'g, G> {
+    type Item = G::Key;
+
+    fn next(&mut self) -> Option<G::Key> {
+        let node = self.queue.pop_front()?;
+        self.graph
+            .for_each_neighbor(&node, self.direction, |_, neighbor| {
+                if !self.discovered.contains(neighbor) {
+                    self.discovered.insert(neighbor.clone());
+                    self.queue.push_back(neighbor.clone());
+                }
+            });
+        Some(node)
+    }
+}
+
+/// A depth-first traversal yielding nodes in pre-order.
+///
+/// Each node is yielded before any of its descendants. Start keys that are
+/// not in the graph are skipped.
+pub struct Dfs<'g, G: Adjacency> {
+    graph: &'g G,
+    stack: Vec<G::Key>,
+    discovered: HashSet<G::Key>,
+    direction: Direction,
+    neighbors: Vec<G::Key>,
+}
+
+impl<'g, G: Adjacency> Dfs<'g, G> {
+    /// Create a depth-first traversal starting at `start`.
+    pub fn new(graph: &'g G, start: G::Key) -> Self {
+        Self::with_starts(graph, [start])
+    }
+
+    /// Create a depth-first traversal starting at each key in `starts`, in order.
+    pub fn with_starts<I>(graph: &'g G, starts: I) -> Self
+    where
+        I: IntoIterator<Item = G::Key>,
+    {
+        let mut stack: Vec<G::Key> = starts
+            .into_iter()
+            .filter(|start| graph.contains_node(start))
+            .collect();
+        stack.reverse();
+
+        Self {
+            graph,
+            stack,
+            discovered: HashSet::new(),
+            direction: Direction::Outgoing,
+            neighbors: Vec::new(),
+        }
+    }
+
+    /// Follow edges in the given `direction`. Traversals walk
+    /// [outgoing](Direction::Outgoing) edges by default.
+    #[must_use]
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+impl<'g, G: Adjacency> Iterator for Dfs<'g, G> {
+    type Item = G::Key;
+
+    fn next(&mut self) -> Option<G::Key> {
+        while let Some(node) = self.stack.pop() {
+            if !self.discovered.insert(node.clone()) {
+                continue;
+            }
+
+            self.graph
+                .for_each_neighbor(&node, self.direction, |_, neighbor| {
+                    if !self.discovered.contains(neighbor) {
+                        self.neighbors.push(neighbor.clone());
+                    }
+                });
+            // Reverse so that the first neighbor is visited first
+            self.stack.extend(self.neighbors.drain(..).rev());
+
+            return Some(node);
+        }
+        None
+    }
+}
+
+/// A depth-first traversal yielding nodes in post-order.
+///
+/// Each node is yielded after all of its descendants. Start keys that are
+/// not in the graph are skipped.
+pub struct DfsPostOrder<'g, G: Adjacency> {
+    graph: &'g G,
+    stack: Vec<G::Key>,
+    discovered: HashSet<G::Key>,
+    finished: HashSet<G::Key>,
+    direction: Direction,
+    neighbors: Vec<G::Key>,
+}
+
+impl<'g, G: Adjacency> DfsPostOrder<'g, G> {
+    /// Create a post-order depth-first traversal starting at `start`.
+    pub fn new(graph: &'g G, start: G::Key) -> Self {
+        Self::with_starts(graph, [start])
+    }
+
+    /// Create a post-order depth-first traversal starting at each key in `starts`, in order.
+    pub fn with_starts<I>(graph: &'g G, starts: I) -> Self
+    where
+        I: IntoIterator<Item = G::Key>,
+    {
+        let mut stack: Vec<G::Key> = starts
+            .into_iter()
+            .filter(|start| graph.contains_node(start))
+            .collect();
+        stack.reverse();
+
+        Self {
+            graph,
+            stack,
+            discovered: HashSet::new(),
+            finished: HashSet::new(),
+            direction: Direction::Outgoing,
+            neighbors: Vec::new(),
+        }
+    }
+
+    /// Follow edges in the given `direction`. Traversals walk
+    /// [outgoing](Direction::Outgoing) edges by default.
+    #[must_use]
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+impl<'g, G: Adjacency> Iterator for DfsPostOrder<'g, G> {
+    type Item = G::Key;
+
+    fn next(&mut self) -> Option<G::Key> {
+        while let Some(node) = self.stack.last().cloned() {
+            if self.discovered.insert(node.clone()) {
+                // First visit: descend into undiscovered neighbors, leaving
+                // the node on the stack until they are finished.
+                self.graph
+                    .for_each_neighbor(&node, self.direction, |_, neighbor| {
+                        if !self.discovered.contains(neighbor) {
+                            self.neighbors.push(neighbor.clone());
+                        }
+                    });
+                self.stack.extend(self.neighbors.drain(..).rev());
+            } else {
+                self.stack.pop();
+                if self.finished.insert(node.clone()) {
+                    return Some(node);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The result of a [parallel breadth-first search](Graph::par_bfs).
+///
+/// Records the distance from the nearest start node and the parent of every
+/// reached node.
+#[cfg(feature = "rayon")]
+pub struct BfsTree<K> {
+    visits: DashMap<K, Visit<K>>,
+}
+
+#[cfg(feature = "rayon")]
+impl<K: Debug + Eq + Hash> Debug for BfsTree<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BfsTree")
+            .field("visits", &self.visits)
+            .finish()
+    }
+}
+
+/// How a node was reached during a [parallel breadth-first search](Graph::par_bfs).
+#[cfg(feature = "rayon")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Visit<K> {
+    /// The number of edges between the node and the nearest start node.
+    pub distance: usize,
+    /// The node this node was discovered from. `None` for start nodes.
+    ///
+    /// When a node can be reached from several nodes at the same distance,
+    /// which one becomes the parent is unspecified.
+    pub parent: Option<K>,
+}
+
+#[cfg(feature = "rayon")]
+impl<K: Eq + Hash + Clone> BfsTree<K> {
+    /// Returns the number of reached nodes.
+    pub fn len(&self) -> usize {
+        self.visits.len()
+    }
+
+    /// Returns `true` if no nodes were reached.
+    pub fn is_empty(&self) -> bool {
+        self.visits.is_empty()
+    }
+
+    /// Returns `true` if `key` was reached.
+    pub fn contains(&self, key: &K) -> bool {
+        self.visits.contains_key(key)
+    }
+
+    /// Returns the distance from the nearest start node to `key`, or `None`
+    /// if it was not reached.
+    pub fn distance(&self, key: &K) -> Option<usize> {
+        self.visits.get(key).map(|visit| visit.distance)
+    }
+
+    /// Returns the node `key` was discovered from, or `None` if `key` is a
+    /// start node or was not reached.
+    pub fn parent(&self, key: &K) -> Option<K> {
+        self.visits.get(key).and_then(|visit| visit.parent.clone())
+    }
+
+    /// Returns the path from a start node to `key`, including both endpoints.
+    pub fn path_to(&self, key: &K) -> Option<Vec<K>> {
+        let mut path = vec![key.clone()];
+        let mut parent = self.visits.get(key)?.parent.clone();
+        while let Some(node) = parent {
+            parent = self.parent(&node);
+            path.push(node);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Consume the tree, returning the map of visited nodes.
+    pub fn into_inner(self) -> DashMap<K, Visit<K>> {
+        self.visits
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<K, V, E, S> Graph<K, V, E, S>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    V: Send + Sync,
+    E: Send + Sync,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    /// Breadth-first search that expands each level of the search in parallel.
+    ///
+    /// The search starts at every key in `starts` and follows edges in the
+    /// given `direction`.
+    ///
+    /// # Example
+    /// ```
+    /// use carpet::{Direction, Graph};
+    ///
+    /// let graph: Graph<u32, ()> = (0..4).map(|i| (i, ())).collect();
+    /// graph.add_edge(0, 1, ());
+    /// graph.add_edge(1, 2, ());
+    ///
+    /// let tree = graph.par_bfs([0], Direction::Outgoing);
+    /// assert_eq!(tree.distance(&2), Some(2));
+    /// assert_eq!(tree.path_to(&2), Some(vec![0, 1, 2]));
+    /// assert!(!tree.contains(&3));
+    /// ```
+    pub fn par_bfs<I>(&self, starts: I, direction: Direction) -> BfsTree<K>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let visits: DashMap<K, Visit<K>> = DashMap::new();
+        let mut frontier: Vec<K> = starts
+            .into_iter()
+            .filter(|start| {
+                self.contains_node(start)
+                    && visits
+                        .insert(
+                            start.clone(),
+                            Visit {
+                                distance: 0,
+                                parent: None,
+                            },
+                        )
+                        .is_none()
+            })
+            .collect();
+
+        let mut distance = 0;
+        while !frontier.is_empty() {
+            distance += 1;
+            frontier = frontier
+                .par_iter()
+                .flat_map_iter(|node| {
+                    let mut discovered = Vec::new();
+                    self.for_each_neighbor(node, direction, |_, neighbor| {
+                        if visits.contains_key(neighbor) {
+                            return;
+                        }
+                        // Another thread may have claimed the neighbor since
+                        // the check above.
+                        if let Entry::Vacant(entry) = visits.entry(neighbor.clone()) {
+                            entry.insert(Visit {
+                                distance,
+                                parent: Some(node.clone()),
+                            });
+                            discovered.push(neighbor.clone());
+                        }
+                    });
+                    discovered
+                })
+                .collect();
+        }
+
+        BfsTree { visits }
+    }
+}